@@ -82,27 +82,24 @@ impl Id {
 pub struct Range {
     start: Id,
     end: Id,
-    range: Vec<Id>,
 }
 
 impl Range {
     pub fn new(start: u64, end: u64) -> Self {
-        let range = (start..=end).map(Id::new).collect();
         Self {
             start: Id::new(start),
             end: Id::new(end),
-            range,
         }
     }
 
     pub fn sum_invalid(&self) -> u64 {
-        self.range
-            .iter()
-            .map(|v| match v.is_valid() {
-                true => 0,
-                false => v.num,
-            })
-            .sum()
+        self.invalid_ids().sum()
+    }
+
+    // lazily walks start..=end and only yields the invalid ids, in ascending order
+    // so this can be chained with take / filter / count without building the range
+    pub fn invalid_ids(&self) -> impl Iterator<Item = u64> {
+        (self.start.num..=self.end.num).filter(|n| !Id::new(*n).is_valid())
     }
 }
 
@@ -138,7 +135,6 @@ mod test {
             Range {
                 start: Id::new(1),
                 end: Id::new(5),
-                range: vec![Id::new(1), Id::new(2), Id::new(3), Id::new(4), Id::new(5)]
             }
         )
     }
@@ -154,4 +150,31 @@ mod test {
         let range = Range::new(11, 22);
         assert_eq!(range.sum_invalid(), 33);
     }
+
+    #[test]
+    fn test_range_invalid_ids() {
+        let range = Range::new(11, 22);
+        assert_eq!(range.invalid_ids().collect::<Vec<u64>>(), vec![11, 22]);
+
+        let range = Range::new(95, 115);
+        assert_eq!(range.invalid_ids().collect::<Vec<u64>>(), vec![99, 111]);
+        assert_eq!(range.invalid_ids().take(1).collect::<Vec<u64>>(), vec![99]);
+        assert_eq!(
+            range
+                .invalid_ids()
+                .filter(|n| n.to_string().starts_with('9'))
+                .count(),
+            1
+        );
+
+        let range = Range::new(1698522, 1698528);
+        assert_eq!(range.invalid_ids().count(), 0);
+
+        // far too big to build up front
+        let range = Range::new(1, u64::MAX);
+        assert_eq!(
+            range.invalid_ids().take(3).collect::<Vec<u64>>(),
+            vec![11, 22, 33]
+        );
+    }
 }
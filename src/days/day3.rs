@@ -8,8 +8,11 @@
 //
 
 use std::{
-    fs::read,
-    io::{BufRead, Result},
+    fmt,
+    fs::File,
+    io::{self, BufRead, BufReader, Result},
+    iter::Sum,
+    ops::Add,
     time::Instant,
 };
 
@@ -20,7 +23,133 @@ pub struct Battery {
 
 pub struct Bank {
     batteries: Vec<Battery>,
-    joltage: u64,
+}
+
+// which batteries were switched on, in order, and the joltage they make
+#[derive(Debug, PartialEq)]
+pub struct Selection {
+    indices: Vec<usize>,
+    joltage: Joltage,
+}
+
+impl Selection {
+    pub fn indices(&self) -> &[usize] {
+        &self.indices
+    }
+
+    pub fn joltage(&self) -> &Joltage {
+        &self.joltage
+    }
+}
+
+// a joltage kept as its decimal digits, most significant first, as picking
+// more than 19 batteries no longer fits in a u64. leading zeros are stripped
+// so zero is the empty list
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Joltage {
+    digits: Vec<u8>,
+}
+
+impl Joltage {
+    pub fn from_digits(digits: Vec<u8>) -> Self {
+        let leading = digits.iter().take_while(|d| **d == 0).count();
+        Self {
+            digits: digits[leading..].to_vec(),
+        }
+    }
+
+    pub fn to_u64(&self) -> Option<u64> {
+        self.digits.iter().try_fold(0u64, |acc, d| {
+            acc.checked_mul(10)
+                .and_then(|acc| acc.checked_add(*d as u64))
+        })
+    }
+}
+
+impl From<u64> for Joltage {
+    fn from(n: u64) -> Self {
+        Self::from_digits(n.to_string().bytes().map(|b| b - b'0').collect())
+    }
+}
+
+impl Add for Joltage {
+    type Output = Self;
+
+    // schoolbook addition from the least significant digit
+    fn add(self, other: Self) -> Self {
+        let mut a = self.digits.iter().rev();
+        let mut b = other.digits.iter().rev();
+        let mut carry = 0;
+        let mut digits = vec![];
+        loop {
+            let (x, y) = (a.next(), b.next());
+            if x.is_none() && y.is_none() && carry == 0 {
+                break;
+            }
+            let s = x.unwrap_or(&0) + y.unwrap_or(&0) + carry;
+            digits.push(s % 10);
+            carry = s / 10;
+        }
+        digits.reverse();
+        Self::from_digits(digits)
+    }
+}
+
+impl Sum for Joltage {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Self::default(), |acc, j| acc + j)
+    }
+}
+
+impl fmt::Display for Joltage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.digits.is_empty() {
+            return write!(f, "0");
+        }
+        self.digits.iter().try_for_each(|d| write!(f, "{}", d))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum BankError {
+    PickExceedsBatteries {
+        pick: usize,
+        batteries: usize,
+    },
+    JoltageOverflow {
+        pick: usize,
+    },
+    InvalidBattery {
+        line: usize,
+        column: usize,
+        found: u8,
+    },
+}
+
+impl fmt::Display for BankError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::PickExceedsBatteries { pick, batteries } => write!(
+                f,
+                "cannot pick {} batteries from a bank of {}",
+                pick, batteries
+            ),
+            Self::JoltageOverflow { pick } => {
+                write!(f, "joltage from picking {} batteries overflows a u64", pick)
+            }
+            Self::InvalidBattery {
+                line,
+                column,
+                found,
+            } => write!(
+                f,
+                "line {} column {}: expected a digit but found {:?}",
+                line,
+                column,
+                char::from(*found)
+            ),
+        }
+    }
 }
 
 impl Battery {
@@ -30,49 +159,168 @@ impl Battery {
 }
 
 impl Bank {
-    fn new(batteries: String) -> Self {
-        let batteries: Vec<Battery> = batteries
-            .chars()
-            .map(|c| {
-                let d = c.to_string().parse().unwrap();
-                Battery::new(d)
-            })
-            .collect();
-        let joltage = Self::calculate_joltage(&batteries);
-        Self { batteries, joltage }
+    // a single bank, so any error is reported on line 1
+    pub fn new(batteries: String) -> std::result::Result<Self, BankError> {
+        check_digits(batteries.as_bytes(), 1)?;
+        let batteries = batteries.bytes().map(|b| Battery::new(b - b'0')).collect();
+        Ok(Self { batteries })
     }
 
-    fn calculate_joltage(batteries: &[Battery]) -> u64 {
-        let mut digits: Vec<u8> = vec![];
-        let mut prev_pos: i16 = -1;
-        for a in (1..=12).rev() {
-            let mut prev: u8 = 0;
-            for (i, j) in batteries
-                .iter()
-                .enumerate()
-                .take(batteries.len() - a + 1)
-                .skip((prev_pos + 1) as usize)
-            {
-                if j.joltage > prev {
-                    prev = j.joltage;
-                    prev_pos = i as i16;
-                }
+    // the joltage from switching on exactly k batteries, keeping their order
+    pub fn joltage(&self, k: usize) -> std::result::Result<u64, BankError> {
+        self.big_joltage(k)?
+            .to_u64()
+            .ok_or(BankError::JoltageOverflow { pick: k })
+    }
+
+    // same as joltage but without the u64 limit
+    pub fn big_joltage(&self, k: usize) -> std::result::Result<Joltage, BankError> {
+        self.check_pick(k)?;
+        Ok(Self::calculate_joltage(&self.batteries, k))
+    }
+
+    pub fn select(&self, k: usize) -> std::result::Result<Selection, BankError> {
+        self.check_pick(k)?;
+        Ok(select(&self.batteries, k, |b| b.joltage))
+    }
+
+    fn check_pick(&self, k: usize) -> std::result::Result<(), BankError> {
+        check_pick(self.batteries.len(), k)
+    }
+
+    // prints the bank with the switched on batteries in bold green
+    pub fn render(&self, selection: &Selection) -> String {
+        render(&self.batteries, selection, |b| b.joltage)
+    }
+
+    fn calculate_joltage(batteries: &[Battery], k: usize) -> Joltage {
+        select(batteries, k, |b| b.joltage).joltage
+    }
+}
+
+// the selection logic works over anything that has a joltage so that the
+// streaming solver can run it straight on the raw digits of a line
+
+fn check_digits(line: &[u8], line_no: usize) -> std::result::Result<(), BankError> {
+    match line.iter().position(|b| !b.is_ascii_digit()) {
+        Some(i) => Err(BankError::InvalidBattery {
+            line: line_no,
+            column: i + 1,
+            found: line[i],
+        }),
+        None => Ok(()),
+    }
+}
+
+fn check_pick(len: usize, k: usize) -> std::result::Result<(), BankError> {
+    if k > len {
+        return Err(BankError::PickExceedsBatteries {
+            pick: k,
+            batteries: len,
+        });
+    }
+    Ok(())
+}
+
+// monotonic stack: we are allowed to throw away len - k batteries, so whenever a
+// bigger digit turns up we pop smaller ones off the top while we still have drops left.
+// popping only on strictly smaller keeps the leftmost of equal digits. once the stack
+// holds k a battery that can't pop anything is one of the drops, so it never grows past k
+fn select<T>(batteries: &[T], k: usize, joltage: impl Fn(&T) -> u8) -> Selection {
+    let mut drops = batteries.len() - k;
+    let mut stack: Vec<usize> = Vec::with_capacity(k);
+    for (i, b) in batteries.iter().enumerate() {
+        while drops > 0
+            && stack
+                .last()
+                .is_some_and(|top| joltage(&batteries[*top]) < joltage(b))
+        {
+            stack.pop();
+            drops -= 1;
+        }
+        if stack.len() < k {
+            stack.push(i);
+        } else {
+            drops -= 1;
+        }
+    }
+
+    let digits = stack.iter().map(|i| joltage(&batteries[*i])).collect();
+    Selection {
+        indices: stack,
+        joltage: Joltage::from_digits(digits),
+    }
+}
+
+fn render<T>(batteries: &[T], selection: &Selection, joltage: impl Fn(&T) -> u8) -> String {
+    let mut chosen = selection.indices.iter().peekable();
+    batteries
+        .iter()
+        .enumerate()
+        .map(|(i, b)| {
+            if chosen.next_if_eq(&&i).is_some() {
+                format!("\x1b[1;32m{}\x1b[0m", joltage(b))
+            } else {
+                joltage(b).to_string()
             }
+        })
+        .collect()
+}
 
-            digits.push(prev);
+// reads banks a line at a time into one reused buffer so memory only grows with
+// the longest bank, not the file. each bank is handed to on_bank along with its
+// selection before being added to the total
+pub fn solve_banks<R: BufRead>(
+    mut reader: R,
+    pick: usize,
+    mut on_bank: impl FnMut(&[u8], &Selection),
+) -> Result<Joltage> {
+    let mut total = Joltage::default();
+    let mut line: Vec<u8> = vec![];
+    let mut line_no = 0;
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        line_no += 1;
+        while line.last().is_some_and(|b| *b == b'\n' || *b == b'\r') {
+            line.pop();
         }
+        if line.is_empty() {
+            continue;
+        }
+
+        check_digits(&line, line_no).map_err(invalid_data)?;
+        line.iter_mut().for_each(|b| *b -= b'0');
+        check_pick(line.len(), pick)
+            .map_err(|e| invalid_data(format!("line {}: {}", line_no, e)))?;
 
-        let s: String = digits.iter().map(|d| d.to_string()).collect();
-        s.parse().unwrap()
+        let selection = select(&line, pick, |d| *d);
+        on_bank(&line, &selection);
+        total = total + selection.joltage;
     }
+    Ok(total)
 }
 
-pub fn day_three(path: &str) -> Result<()> {
+fn invalid_data(e: impl ToString) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
+
+pub fn day_three(path: &str, pick: usize, render_banks: bool) -> Result<()> {
     let now = Instant::now();
 
-    let content = read(path)?;
+    let reader = BufReader::new(File::open(path)?);
 
-    let answer: u64 = content.lines().map(|l| Bank::new(l.unwrap()).joltage).sum();
+    let answer = solve_banks(reader, pick, |digits, selection| {
+        if render_banks {
+            println!(
+                "{} -> {}",
+                render(digits, selection, |d| *d),
+                selection.joltage
+            );
+        }
+    })?;
 
     println!(
         "the answer is {} and it took {}",
@@ -86,6 +334,7 @@ pub fn day_three(path: &str) -> Result<()> {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::days::test_util::Rng;
 
     #[test]
     fn test_battery_new() {
@@ -94,11 +343,97 @@ mod test {
 
     #[test]
     fn test_bank_new() {
-        let bank = Bank::new("987654321111111".to_string());
-        assert_eq!(bank.joltage, 987654321111);
+        let bank = Bank::new("987654321111111".to_string()).unwrap();
+        assert_eq!(bank.joltage(12), Ok(987654321111));
+
+        let bank = Bank::new("818181911112111".to_string()).unwrap();
+        assert_eq!(bank.joltage(12), Ok(888911112111));
+    }
+
+    #[test]
+    fn test_bank_joltage() {
+        let bank = Bank::new("987654321111111".to_string()).unwrap();
+        assert_eq!(bank.joltage(2), Ok(98));
+        assert_eq!(bank.joltage(0), Ok(0));
+
+        let bank = Bank::new("811111111111119".to_string()).unwrap();
+        assert_eq!(bank.joltage(2), Ok(89));
+        assert_eq!(bank.joltage(15), Ok(811111111111119));
+        assert_eq!(
+            bank.joltage(16),
+            Err(BankError::PickExceedsBatteries {
+                pick: 16,
+                batteries: 15
+            })
+        );
+    }
+
+    #[test]
+    fn test_bank_big_joltage() {
+        let bank = Bank::new("9".repeat(25)).unwrap();
+        assert_eq!(bank.big_joltage(21).unwrap().to_string(), "9".repeat(21));
+        assert_eq!(
+            bank.joltage(21),
+            Err(BankError::JoltageOverflow { pick: 21 })
+        );
+        assert_eq!(bank.joltage(19), Ok(9999999999999999999));
+    }
+
+    #[test]
+    fn test_joltage_from_digits() {
+        assert_eq!(Joltage::from_digits(vec![0, 0, 1, 2]), Joltage::from(12));
+        assert_eq!(Joltage::from_digits(vec![0, 0]), Joltage::default());
+        assert_eq!(Joltage::default().to_string(), "0");
+    }
+
+    #[test]
+    fn test_joltage_add() {
+        assert_eq!(Joltage::from(95) + Joltage::from(7), Joltage::from(102));
+        assert_eq!(Joltage::default() + Joltage::from(7), Joltage::from(7));
+
+        let big = Joltage::from_digits(vec![9; 25]);
+        let mut expected = vec![1];
+        expected.extend(vec![9; 24]);
+        expected.push(8);
+        assert_eq!(big.clone() + big, Joltage::from_digits(expected));
+
+        let total: Joltage = vec![Joltage::from(u64::MAX), Joltage::from(1)]
+            .into_iter()
+            .sum();
+        assert_eq!(total.to_string(), "18446744073709551616");
+        assert_eq!(total.to_u64(), None);
+    }
+
+    #[test]
+    fn test_bank_select() {
+        let bank = Bank::new("818181911112111".to_string()).unwrap();
+        assert_eq!(
+            bank.select(4),
+            Ok(Selection {
+                indices: vec![6, 11, 12, 13],
+                joltage: Joltage::from(9211)
+            })
+        );
+
+        // equal digits take the leftmost ones first
+        let bank = Bank::new("81818".to_string()).unwrap();
+        assert_eq!(
+            bank.select(2),
+            Ok(Selection {
+                indices: vec![0, 2],
+                joltage: Joltage::from(88)
+            })
+        );
+    }
 
-        let bank = Bank::new("818181911112111".to_string());
-        assert_eq!(bank.joltage, 888911112111);
+    #[test]
+    fn test_bank_render() {
+        let bank = Bank::new("1291".to_string()).unwrap();
+        let selection = bank.select(2).unwrap();
+        assert_eq!(
+            bank.render(&selection),
+            "12\x1b[1;32m9\x1b[0m\x1b[1;32m1\x1b[0m"
+        );
     }
 
     #[test]
@@ -117,6 +452,118 @@ mod test {
             Battery::new(2),
             Battery::new(3),
         ];
-        assert_eq!(Bank::calculate_joltage(&batteries), 123123123123);
+        assert_eq!(
+            Bank::calculate_joltage(&batteries, 12),
+            Joltage::from(123123123123)
+        );
+    }
+
+    #[test]
+    fn test_bank_new_invalid() {
+        assert_eq!(
+            Bank::new("12a4".to_string()).err(),
+            Some(BankError::InvalidBattery {
+                line: 1,
+                column: 3,
+                found: b'a'
+            })
+        );
+    }
+
+    #[test]
+    fn test_selection_accessors() {
+        let selection = Bank::new("818181911112111".to_string())
+            .unwrap()
+            .select(4)
+            .unwrap();
+        assert_eq!(selection.indices(), &[6, 11, 12, 13]);
+        assert_eq!(selection.joltage(), &Joltage::from(9211));
+    }
+
+    #[test]
+    fn test_solve_banks() {
+        let input = "987654321111111\r\n811111111111119\n\n234234234234278\n818181911112111";
+        let total = solve_banks(input.as_bytes(), 12, |_, _| {}).unwrap();
+        assert_eq!(total, Joltage::from(3121910778619));
+
+        let mut seen = vec![];
+        let total = solve_banks(input.as_bytes(), 2, |digits, selection| {
+            seen.push((digits.len(), selection.indices().to_vec()))
+        })
+        .unwrap();
+        assert_eq!(total, Joltage::from(357));
+        assert_eq!(
+            seen,
+            vec![
+                (15, vec![0, 1]),
+                (15, vec![0, 14]),
+                (15, vec![13, 14]),
+                (15, vec![6, 11])
+            ]
+        );
+    }
+
+    #[test]
+    fn test_solve_banks_invalid() {
+        let input = "12345\n12a45\n";
+        let err = solve_banks(input.as_bytes(), 2, |_, _| {}).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(
+            err.to_string(),
+            BankError::InvalidBattery {
+                line: 2,
+                column: 3,
+                found: b'a'
+            }
+            .to_string()
+        );
+
+        let input = "12345\n12\n";
+        let err = solve_banks(input.as_bytes(), 3, |_, _| {}).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "line 2: cannot pick 3 batteries from a bank of 2"
+        );
+    }
+
+    // the original O(n*k) scan, kept to cross check the stack version
+    fn greedy_joltage(batteries: &[Battery], k: usize) -> u64 {
+        let mut value = 0;
+        let mut start = 0;
+        for a in (1..=k).rev() {
+            let mut prev: u8 = 0;
+            for (i, j) in batteries
+                .iter()
+                .enumerate()
+                .take(batteries.len() - a + 1)
+                .skip(start)
+            {
+                if j.joltage > prev {
+                    prev = j.joltage;
+                    start = i + 1;
+                }
+            }
+            value = value * 10 + prev as u64;
+        }
+        value
+    }
+
+    #[test]
+    fn test_bank_calculate_joltage_matches_greedy() {
+        let mut rng = Rng::new(0x2545f4914f6cdd1d);
+        let mut next = || rng.next_u64();
+
+        for _ in 0..500 {
+            let len = (next() % 40) as usize + 1;
+            let batteries: Vec<Battery> = (0..len)
+                .map(|_| Battery::new((next() % 9) as u8 + 1))
+                .collect();
+            for k in 0..=len.min(19) {
+                assert_eq!(
+                    Bank::calculate_joltage(&batteries, k),
+                    Joltage::from(greedy_joltage(&batteries, k))
+                );
+            }
+        }
     }
 }
@@ -1,6 +1,9 @@
 use std::{
-    fs::{read, read_to_string},
-    io::Result,
+    collections::{HashMap, HashSet, VecDeque},
+    fmt,
+    fs::read_to_string,
+    io::{self, Result},
+    ops::Range,
     slice::Iter,
     time::Instant,
 };
@@ -9,13 +12,13 @@ use std::{
 // we just need to count how many have less than 4 papers nearby
 //
 //
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub struct Position {
     x: usize,
     y: usize,
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum Tile {
     Space,
     Paper,
@@ -33,6 +36,20 @@ pub enum Direction {
 }
 
 impl Direction {
+    // (dx, dy) with y growing downwards
+    pub fn offset(&self) -> (isize, isize) {
+        match self {
+            Self::Above => (0, -1),
+            Self::Left => (-1, 0),
+            Self::Below => (0, 1),
+            Self::Right => (1, 0),
+            Self::TopLeft => (-1, -1),
+            Self::TopRight => (1, -1),
+            Self::BottomLeft => (-1, 1),
+            Self::BottomRight => (1, 1),
+        }
+    }
+
     pub fn iterator() -> Iter<'static, Direction> {
         static DIRECTIONS: [Direction; 8] = [
             Direction::Above,
@@ -48,12 +65,88 @@ impl Direction {
     }
 }
 
+// which cells count as neighbours of a roll
+#[derive(Debug, PartialEq, Clone)]
+pub enum Neighbourhood {
+    Moore,      // all eight around it
+    VonNeumann, // just above, below, left and right
+    Custom(Vec<(isize, isize)>),
+}
+
+impl Neighbourhood {
+    // "moore", "von-neumann" or offsets like "1,0;-1,0;0,2"
+    pub fn read(s: &str) -> Option<Self> {
+        match s {
+            "moore" => Some(Self::Moore),
+            "von-neumann" => Some(Self::VonNeumann),
+            _ => s
+                .split(';')
+                .map(|pair| {
+                    let (dx, dy) = pair.split_once(',')?;
+                    Some((dx.trim().parse().ok()?, dy.trim().parse().ok()?))
+                })
+                .collect::<Option<Vec<(isize, isize)>>>()
+                .map(Self::Custom),
+        }
+    }
+
+    pub fn offsets(&self) -> Vec<(isize, isize)> {
+        match self {
+            Self::Moore => Direction::iterator().map(|d| d.offset()).collect(),
+            Self::VonNeumann => [
+                Direction::Above,
+                Direction::Left,
+                Direction::Below,
+                Direction::Right,
+            ]
+            .iter()
+            .map(|d| d.offset())
+            .collect(),
+            Self::Custom(offsets) => offsets.clone(),
+        }
+    }
+}
+
+// a roll can be removed when it has fewer than threshold paper neighbours
+#[derive(Debug, PartialEq, Clone)]
+pub struct RemovalRule {
+    threshold: usize,
+    neighbourhood: Neighbourhood,
+}
+
+impl RemovalRule {
+    pub fn new(threshold: usize, neighbourhood: Neighbourhood) -> Self {
+        Self {
+            threshold,
+            neighbourhood,
+        }
+    }
+}
+
+// the forklift rule from the puzzle
+impl Default for RemovalRule {
+    fn default() -> Self {
+        Self::new(4, Neighbourhood::Moore)
+    }
+}
+
 impl Tile {
     pub fn from_char(c: char) -> Self {
+        Self::read(c).expect("neither space nor paper")
+    }
+
+    pub fn read(c: char) -> Option<Self> {
         match c {
-            '.' => Self::Space,
-            '@' => Self::Paper,
-            _ => panic!("neither space nor paper"),
+            '.' => Some(Self::Space),
+            '@' => Some(Self::Paper),
+            _ => None,
+        }
+    }
+
+    pub fn to_char(&self) -> char {
+        match self {
+            Self::Space => '.',
+            Self::Paper => '@',
         }
     }
 }
@@ -63,6 +156,25 @@ impl Position {
         Self { x, y }
     }
 
+    pub fn offset(&self, (dx, dy): (isize, isize), max_x: usize, max_y: usize) -> Option<Self> {
+        let x = self.x.checked_add_signed(dx)?;
+        let y = self.y.checked_add_signed(dy)?;
+        if x > max_x || y > max_y {
+            return None;
+        }
+        Some(Self { x, y })
+    }
+
+    // offset but leaving one edge brings you back in on the opposite edge. the offset
+    // is brought inside the grid first so a huge one can't overflow
+    pub fn wrapping_offset(&self, (dx, dy): (isize, isize), max_x: usize, max_y: usize) -> Self {
+        let (width, height) = (max_x + 1, max_y + 1);
+        Self {
+            x: (self.x + dx.rem_euclid(width as isize) as usize) % width,
+            y: (self.y + dy.rem_euclid(height as isize) as usize) % height,
+        }
+    }
+
     pub fn look(&self, direction: &Direction, max_x: usize, max_y: usize) -> Option<Self> {
         match direction {
             Direction::Above => {
@@ -133,102 +245,773 @@ impl Position {
     }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub struct Grid {
     grid: Vec<Vec<Tile>>,
+    wrap: bool, // toroidal, so the edges see the opposite edges as neighbours
+}
+
+impl fmt::Display for Grid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for row in self.grid.iter() {
+            let line: String = row.iter().map(Tile::to_char).collect();
+            writeln!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+// which rolls came off in each round of step_until, round 1 first
+#[derive(Debug, PartialEq, Default)]
+pub struct History {
+    rounds: Vec<Vec<Position>>,
+    removed_in: HashMap<Position, usize>,
+}
+
+impl History {
+    fn record(&mut self, pos: Position, round: usize) {
+        if self.rounds.len() < round {
+            self.rounds.resize(round, vec![]);
+        }
+        self.rounds[round - 1].push(pos.clone());
+        self.removed_in.insert(pos, round);
+    }
+
+    // the round (x, y) was removed in, if it ever was
+    pub fn round_of(&self, x: usize, y: usize) -> Option<usize> {
+        self.removed_in.get(&Position::new(x, y)).copied()
+    }
+
+    pub fn counts(&self) -> Vec<usize> {
+        self.rounds.iter().map(|r| r.len()).collect()
+    }
+
+    // the grid after each round, starting from the grid before any were removed
+    pub fn replay(&self, grid: &Grid) -> Vec<Grid> {
+        let mut grid = grid.clone();
+        self.rounds
+            .iter()
+            .map(|round| {
+                for p in round.iter() {
+                    grid.grid[p.y][p.x] = Tile::Space;
+                }
+                grid.clone()
+            })
+            .collect()
+    }
+}
+
+// rows and columns are counted from 1 to match an editor
+#[derive(Debug, PartialEq)]
+pub enum GridError {
+    Empty,
+    RaggedRow {
+        row: usize,
+        expected: usize,
+        found: usize,
+    },
+    InvalidTile {
+        row: usize,
+        column: usize,
+        found: char,
+    },
+    InvalidCoordinate {
+        row: usize,
+    },
+    WrapWithoutBounds,
+    HistoryWithoutGrid,
+}
+
+impl fmt::Display for GridError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(f, "the grid has no tiles"),
+            Self::RaggedRow {
+                row,
+                expected,
+                found,
+            } => write!(
+                f,
+                "row {} has {} tiles but the first row has {}",
+                row, found, expected
+            ),
+            Self::InvalidTile { row, column, found } => write!(
+                f,
+                "row {} column {}: expected '.' or '@' but found {:?}",
+                row, column, found
+            ),
+            Self::InvalidCoordinate { row } => write!(f, "row {}: expected x,y", row),
+            Self::WrapWithoutBounds => write!(f, "a list of rolls has no edges to wrap around"),
+            Self::HistoryWithoutGrid => write!(
+                f,
+                "history replays a grid of tiles, a list of rolls has no grid to draw"
+            ),
+        }
+    }
 }
 
 impl Grid {
+    // panics on a malformed grid, use read to get the error instead
     pub fn new(grid_str: &str) -> Self {
-        let grid: Vec<Vec<Tile>> = grid_str
-            .trim_end_matches('\n')
-            .split('\n')
-            .map(|row| row.chars().map(Tile::from_char).collect())
-            .collect();
-        Self { grid }
+        Self::read(grid_str).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    pub fn read(grid_str: &str) -> std::result::Result<Self, GridError> {
+        let mut width = None;
+        let grid = grid_str
+            .trim_end_matches(['\n', '\r'])
+            .lines()
+            .enumerate()
+            .map(|(j, row)| {
+                let tiles = row
+                    .chars()
+                    .enumerate()
+                    .map(|(i, c)| {
+                        Tile::read(c).ok_or(GridError::InvalidTile {
+                            row: j + 1,
+                            column: i + 1,
+                            found: c,
+                        })
+                    })
+                    .collect::<std::result::Result<Vec<Tile>, GridError>>()?;
+                let expected = *width.get_or_insert(tiles.len());
+                if tiles.len() != expected {
+                    return Err(GridError::RaggedRow {
+                        row: j + 1,
+                        expected,
+                        found: tiles.len(),
+                    });
+                }
+                Ok(tiles)
+            })
+            .collect::<std::result::Result<Vec<Vec<Tile>>, GridError>>()?;
+
+        if width.is_none_or(|w| w == 0) {
+            return Err(GridError::Empty);
+        }
+        Ok(Self { grid, wrap: false })
+    }
+
+    pub fn with_wrap(self, wrap: bool) -> Self {
+        Self { wrap, ..self }
+    }
+
+    fn neighbour(
+        &self,
+        pos: &Position,
+        offset: (isize, isize),
+        max_x: usize,
+        max_y: usize,
+    ) -> Option<Position> {
+        if self.wrap {
+            return Some(pos.wrapping_offset(offset, max_x, max_y));
+        }
+        pos.offset(offset, max_x, max_y)
+    }
+
+    // how many of the neighbours of pos are paper
+    fn neighbours(
+        &self,
+        pos: &Position,
+        offsets: &[(isize, isize)],
+        max_x: usize,
+        max_y: usize,
+    ) -> usize {
+        offsets
+            .iter()
+            .filter_map(|o| self.neighbour(pos, *o, max_x, max_y))
+            .filter(|p| self.grid.get(p.y).unwrap().get(p.x).unwrap() == &Tile::Paper)
+            .count()
     }
 
-    pub fn score(&self) -> u64 {
+    // every roll of paper that the rule says can be removed
+    fn accessible(&self, rule: &RemovalRule) -> Vec<Position> {
         let max_y = self.grid.len() - 1;
         let max_x = self.grid.first().unwrap().len() - 1;
-        let mut score = 0;
+        let offsets = rule.neighbourhood.offsets();
+        let mut accessible: Vec<Position> = vec![];
 
         for (j, row) in self.grid.iter().enumerate() {
             for (i, _) in row.iter().enumerate().filter(|(_, t)| t == &&Tile::Paper) {
                 let pos = Position::new(i, j);
-                let mut local_score = 0;
-                for d in Direction::iterator() {
-                    let new_pos = pos.look(d, max_x, max_y);
-                    if let Some(p) = new_pos {
-                        if self.grid.get(p.y).unwrap().get(p.x).unwrap() == &Tile::Paper {
-                            local_score += 1;
-                        }
-                    };
-                }
-                if local_score < 4 {
-                    score += 1;
+                if self.neighbours(&pos, &offsets, max_x, max_y) < rule.threshold {
+                    accessible.push(pos);
                 }
             }
         }
-        score
+        accessible
+    }
+
+    pub fn score(&self, rule: &RemovalRule) -> u64 {
+        self.accessible(rule).len() as u64
     }
 
     // this function will take a grid of tiles and remove the removable ones
-    pub fn step(&mut self) -> usize {
+    pub fn step(&mut self, rule: &RemovalRule) -> usize {
         // given a state see which ones can be removed
         // remove them
+        let remove_buffer = self.accessible(rule);
+
+        for p in remove_buffer.iter() {
+            let p_change = self.grid.get_mut(p.y).unwrap().get_mut(p.x).unwrap();
+            *p_change = Tile::Space;
+        }
+
+        remove_buffer.len()
+    }
+
+    pub fn step_until(&mut self, rule: &RemovalRule) -> usize {
+        self.remove_all(rule, None)
+    }
+
+    // step_until but also keeping which round each roll was removed in
+    pub fn step_until_recorded(&mut self, rule: &RemovalRule) -> History {
+        let mut history = History::default();
+        self.remove_all(rule, Some(&mut history));
+        history
+    }
+
+    // rather than rescanning the grid every round we keep the neighbour count of every
+    // roll and a queue of rolls that can be removed. removing a roll only changes the
+    // counts of the cells that see it as a neighbour so those are the only ones that can
+    // join the queue. removal never adds paper so the order doesnt change the final grid.
+    // the queue is first in first out so rolls come off in round order, and a roll that
+    // becomes removable while round r is coming off is part of round r + 1
+    fn remove_all(&mut self, rule: &RemovalRule, mut history: Option<&mut History>) -> usize {
         let max_y = self.grid.len() - 1;
         let max_x = self.grid.first().unwrap().len() - 1;
-        let mut remove_buffer: Vec<Position> = vec![];
+        let offsets = rule.neighbourhood.offsets();
+        // custom neighbourhoods neednt be symmetric so walk the offsets backwards
+        // to find who is watching a removed roll. without wrap an offset too big to
+        // negate never lands on the grid, with wrap only its remainder matters
+        let (width, height) = (max_x as isize + 1, max_y as isize + 1);
+        let watchers: Vec<(isize, isize)> = offsets
+            .iter()
+            .filter_map(|(dx, dy)| {
+                if self.wrap {
+                    Some((-dx.rem_euclid(width), -dy.rem_euclid(height)))
+                } else {
+                    Some((dx.checked_neg()?, dy.checked_neg()?))
+                }
+            })
+            .collect();
 
-        for (j, row) in self.grid.iter().enumerate() {
-            for (i, _) in row.iter().enumerate().filter(|(_, t)| t == &&Tile::Paper) {
-                let pos = Position::new(i, j);
-                let mut local_score = 0;
-                for d in Direction::iterator() {
-                    let new_pos = pos.look(d, max_x, max_y);
-                    if let Some(p) = new_pos {
-                        if self.grid.get(p.y).unwrap().get(p.x).unwrap() == &Tile::Paper {
-                            local_score += 1;
-                        }
-                    };
+        let mut counts: Vec<Vec<usize>> = self
+            .grid
+            .iter()
+            .enumerate()
+            .map(|(j, row)| {
+                (0..row.len())
+                    .map(|i| self.neighbours(&Position::new(i, j), &offsets, max_x, max_y))
+                    .collect()
+            })
+            .collect();
+
+        let mut queue: VecDeque<(Position, usize)> =
+            self.accessible(rule).into_iter().map(|p| (p, 1)).collect();
+        let mut score = 0;
+
+        while let Some((pos, round)) = queue.pop_front() {
+            let tile = self.grid.get_mut(pos.y).unwrap().get_mut(pos.x).unwrap();
+            if tile == &Tile::Space {
+                continue;
+            }
+            *tile = Tile::Space;
+            score += 1;
+            if let Some(h) = history.as_deref_mut() {
+                h.record(pos.clone(), round);
+            }
+
+            for p in watchers
+                .iter()
+                .filter_map(|o| self.neighbour(&pos, *o, max_x, max_y))
+            {
+                let count = counts.get_mut(p.y).unwrap().get_mut(p.x).unwrap();
+                *count -= 1;
+                // only queue it the moment it drops below the threshold, so it goes in once
+                if *count + 1 == rule.threshold
+                    && self.grid.get(p.y).unwrap().get(p.x).unwrap() == &Tile::Paper
+                {
+                    queue.push_back((p, round + 1));
                 }
-                if local_score < 4 {
-                    remove_buffer.push(pos);
+            }
+        }
+        score
+    }
+}
+
+// the same grid packed into bits, 64 cells to a word with bit x of a row being
+// column x. bits past the width are always kept at zero. neighbour counts for a
+// whole row are worked out at once by lining up a shifted copy of the row for each
+// offset and adding them into a bit sliced counter, so plane i holds bit i of the
+// count for every cell in the row
+#[derive(Debug, PartialEq, Clone)]
+pub struct BitGrid {
+    width: usize,
+    height: usize,
+    rows: Vec<Vec<u64>>,
+    wrap: bool,
+}
+
+impl From<&Grid> for BitGrid {
+    fn from(grid: &Grid) -> Self {
+        let width = grid.grid.first().map_or(0, |r| r.len());
+        let rows = grid
+            .grid
+            .iter()
+            .map(|row| {
+                let mut words = vec![0u64; width.div_ceil(64)];
+                for (x, _) in row.iter().enumerate().filter(|(_, t)| t == &&Tile::Paper) {
+                    words[x / 64] |= 1 << (x % 64);
                 }
+                words
+            })
+            .collect();
+        Self {
+            width,
+            height: grid.grid.len(),
+            rows,
+            wrap: grid.wrap,
+        }
+    }
+}
+
+impl From<&BitGrid> for Grid {
+    fn from(bits: &BitGrid) -> Self {
+        let grid = bits
+            .rows
+            .iter()
+            .map(|words| {
+                (0..bits.width)
+                    .map(|x| match words[x / 64] >> (x % 64) & 1 {
+                        1 => Tile::Paper,
+                        _ => Tile::Space,
+                    })
+                    .collect()
+            })
+            .collect();
+        Self {
+            grid,
+            wrap: bits.wrap,
+        }
+    }
+}
+
+impl BitGrid {
+    pub fn new(grid_str: &str) -> Self {
+        Self::from(&Grid::new(grid_str))
+    }
+
+    pub fn with_wrap(self, wrap: bool) -> Self {
+        Self { wrap, ..self }
+    }
+
+    // out[x] = row[x + dx] for the words of out starting at word lo, anything off the
+    // row reads as empty
+    fn shift_into(&self, row: &[u64], dx: isize, lo: usize, out: &mut [u64]) {
+        let words = row.len() as isize;
+        let (word_shift, bit_shift) = (dx.div_euclid(64), dx.rem_euclid(64) as u32);
+        // None is a word so far off the row it doesnt fit in an isize
+        let word = |i: Option<isize>| match i {
+            Some(i) if i >= 0 && i < words => row[i as usize],
+            _ => 0,
+        };
+        for (w, o) in (lo..).zip(out.iter_mut()) {
+            let i = (w as isize).checked_add(word_shift);
+            *o = word(i) >> bit_shift;
+            if bit_shift != 0 {
+                *o |= word(i.and_then(|i| i.checked_add(1))) << (64 - bit_shift);
             }
         }
+        if lo + out.len() == row.len()
+            && let Some(last) = out.last_mut()
+            && !self.width.is_multiple_of(64)
+        {
+            *last &= (1 << (self.width % 64)) - 1;
+        }
+    }
 
-        for p in remove_buffer.iter() {
-            let p_change = self.grid.get_mut(p.y).unwrap().get_mut(p.x).unwrap();
-            *p_change = Tile::Space;
+    // the cells sitting at (dx, dy) from each cell of row y, false if that is off the grid
+    fn neighbour_row(
+        &self,
+        y: usize,
+        (dx, dy): (isize, isize),
+        lo: usize,
+        out: &mut [u64],
+    ) -> bool {
+        let (width, height) = (self.width as isize, self.height as isize);
+        let ny = if self.wrap {
+            (y + dy.rem_euclid(height) as usize) % self.height
+        } else {
+            match (y as isize).checked_add(dy) {
+                Some(ny) if ny >= 0 && ny < height => ny as usize,
+                _ => return false,
+            }
+        };
+        let row = &self.rows[ny];
+        if !self.wrap {
+            self.shift_into(row, dx, lo, out);
+            return true;
+        }
+        // whatever runs off one side comes back in from the other
+        let dx = dx.rem_euclid(width);
+        self.shift_into(row, dx, lo, out);
+        let mut far = vec![0; out.len()];
+        self.shift_into(row, dx - width, lo, &mut far);
+        out.iter_mut().zip(far).for_each(|(a, b)| *a |= b);
+        true
+    }
+
+    // for the given words of row y, which rolls of paper the rule says can be removed
+    fn accessible_words(
+        &self,
+        y: usize,
+        rule: &RemovalRule,
+        offsets: &[(isize, isize)],
+        words: Range<usize>,
+    ) -> Vec<u64> {
+        let most = offsets.len().max(rule.threshold).max(1);
+        let mut planes =
+            vec![vec![0u64; words.len()]; (usize::BITS - most.leading_zeros()) as usize];
+        let mut n = vec![0u64; words.len()];
+
+        for o in offsets.iter() {
+            if !self.neighbour_row(y, *o, words.start, &mut n) {
+                continue;
+            }
+            // ripple n into the counter a plane at a time, n is left holding the carry
+            for plane in planes.iter_mut() {
+                for (p, c) in plane.iter_mut().zip(n.iter_mut()) {
+                    (*p, *c) = (*p ^ *c, *p & *c);
+                }
+            }
+        }
+
+        // count < threshold, comparing from the top bit down
+        words
+            .enumerate()
+            .map(|(i, w)| {
+                let (mut lt, mut eq) = (0u64, u64::MAX);
+                for (b, plane) in planes.iter().enumerate().rev() {
+                    if rule.threshold >> b & 1 == 1 {
+                        lt |= eq & !plane[i];
+                        eq &= plane[i];
+                    } else {
+                        eq &= !plane[i];
+                    }
+                }
+                lt & self.rows[y][w]
+            })
+            .collect()
+    }
+
+    fn accessible(&self, rule: &RemovalRule) -> Vec<Vec<u64>> {
+        let offsets = rule.neighbourhood.offsets();
+        (0..self.height)
+            .map(|y| self.accessible_words(y, rule, &offsets, 0..self.rows[y].len()))
+            .collect()
+    }
+
+    pub fn score(&self, rule: &RemovalRule) -> u64 {
+        self.accessible(rule)
+            .iter()
+            .flatten()
+            .map(|w| w.count_ones() as u64)
+            .sum()
+    }
+
+    pub fn step(&mut self, rule: &RemovalRule) -> usize {
+        let remove = self.accessible(rule);
+        let mut n = 0;
+        for (row, remove_row) in self.rows.iter_mut().zip(remove) {
+            for (w, r) in row.iter_mut().zip(remove_row) {
+                n += r.count_ones() as usize;
+                *w &= !r;
+            }
+        }
+        n
+    }
+
+    // rounds of step, but a word can only gain removable rolls if one of the words it
+    // looks at lost some, so after the first round only those words are recounted.
+    // dirty holds the inclusive range of words to recount in each row
+    pub fn step_until(&mut self, rule: &RemovalRule) -> usize {
+        let offsets = rule.neighbourhood.offsets();
+        let (height, words) = (self.height as isize, self.width.div_ceil(64));
+        let mut dirty: Vec<Option<(usize, usize)>> =
+            vec![words.checked_sub(1).map(|hi| (0, hi)); self.height];
+        let mut score = 0;
+        loop {
+            let remove: Vec<(usize, usize, Vec<u64>)> = (0..self.height)
+                .filter_map(|y| dirty[y].map(|(lo, hi)| (y, lo, hi)))
+                .map(|(y, lo, hi)| (y, lo, self.accessible_words(y, rule, &offsets, lo..hi + 1)))
+                .filter(|(_, _, r)| r.iter().any(|w| *w != 0))
+                .collect();
+            if remove.is_empty() {
+                return score;
+            }
+
+            dirty.fill(None);
+            let mut mark = |y: isize, lo: isize, hi: isize| {
+                let (lo, hi) = (lo.max(0), hi.min(words as isize - 1));
+                if y < 0 || y >= height || lo > hi {
+                    return;
+                }
+                let d = &mut dirty[y as usize];
+                *d = Some(match d {
+                    Some((a, b)) => ((*a).min(lo as usize), (*b).max(hi as usize)),
+                    None => (lo as usize, hi as usize),
+                });
+            };
+            for (y, lo, remove_row) in remove {
+                for (w, r) in (lo..).zip(remove_row) {
+                    if r == 0 {
+                        continue;
+                    }
+                    score += r.count_ones() as usize;
+                    self.rows[y][w] &= !r;
+
+                    for (dx, dy) in offsets.iter() {
+                        if self.wrap {
+                            // wrapping can carry a change to the far end of the row
+                            let watcher = (y as isize - dy.rem_euclid(height)).rem_euclid(height);
+                            mark(watcher, 0, words as isize);
+                        } else {
+                            // too far away to be on the grid at all
+                            let Some(watcher) = (y as isize).checked_sub(*dy) else {
+                                continue;
+                            };
+                            // word w of the row is read by words w - shift and the one below
+                            let from = w as isize - dx.div_euclid(64);
+                            mark(watcher, from - 1, from);
+                        }
+                    }
+                }
+            }
         }
+    }
+}
+
+// just the coordinates of the rolls, for a floor that is mostly empty. there are no
+// edges so coordinates can be anywhere, negative included
+#[derive(Debug, PartialEq, Clone)]
+pub struct SparseGrid {
+    rolls: HashSet<(i64, i64)>,
+}
+
+impl From<&Grid> for SparseGrid {
+    fn from(grid: &Grid) -> Self {
+        let rolls = grid
+            .grid
+            .iter()
+            .enumerate()
+            .flat_map(|(j, row)| {
+                row.iter()
+                    .enumerate()
+                    .filter(|(_, t)| t == &&Tile::Paper)
+                    .map(move |(i, _)| (i as i64, j as i64))
+            })
+            .collect();
+        Self { rolls }
+    }
+}
+
+impl SparseGrid {
+    pub fn new(rolls: HashSet<(i64, i64)>) -> Self {
+        Self { rolls }
+    }
+
+    // one roll per line as x,y
+    pub fn read(rolls_str: &str) -> std::result::Result<Self, GridError> {
+        let rolls = rolls_str
+            .lines()
+            .enumerate()
+            .filter(|(_, l)| !l.trim().is_empty())
+            .map(|(j, l)| {
+                let (x, y) = l
+                    .split_once(',')
+                    .ok_or(GridError::InvalidCoordinate { row: j + 1 })?;
+                match (x.trim().parse(), y.trim().parse()) {
+                    (Ok(x), Ok(y)) => Ok((x, y)),
+                    _ => Err(GridError::InvalidCoordinate { row: j + 1 }),
+                }
+            })
+            .collect::<std::result::Result<HashSet<(i64, i64)>, GridError>>()?;
+        Ok(Self { rolls })
+    }
+
+    // None when the neighbour would be off the end of i64, there is no roll there
+    fn offset((x, y): (i64, i64), (dx, dy): (isize, isize)) -> Option<(i64, i64)> {
+        Some((x.checked_add(dx as i64)?, y.checked_add(dy as i64)?))
+    }
+
+    // the roll that sees this one through the offset
+    fn watcher((x, y): (i64, i64), (dx, dy): (isize, isize)) -> Option<(i64, i64)> {
+        Some((x.checked_sub(dx as i64)?, y.checked_sub(dy as i64)?))
+    }
+
+    fn neighbours(&self, roll: (i64, i64), offsets: &[(isize, isize)]) -> usize {
+        offsets
+            .iter()
+            .filter_map(|d| Self::offset(roll, *d))
+            .filter(|r| self.rolls.contains(r))
+            .count()
+    }
+
+    fn accessible(&self, rule: &RemovalRule) -> Vec<(i64, i64)> {
+        let offsets = rule.neighbourhood.offsets();
+        self.rolls
+            .iter()
+            .filter(|r| self.neighbours(**r, &offsets) < rule.threshold)
+            .copied()
+            .collect()
+    }
+
+    pub fn score(&self, rule: &RemovalRule) -> u64 {
+        self.accessible(rule).len() as u64
+    }
 
+    pub fn step(&mut self, rule: &RemovalRule) -> usize {
+        let remove_buffer = self.accessible(rule);
+        for r in remove_buffer.iter() {
+            self.rolls.remove(r);
+        }
         remove_buffer.len()
     }
 
-    pub fn step_until(&mut self) -> usize {
-        let mut n = 1;
+    // the same worklist as Grid::step_until, keyed by coordinate
+    pub fn step_until(&mut self, rule: &RemovalRule) -> usize {
+        let offsets = rule.neighbourhood.offsets();
+        let mut counts: HashMap<(i64, i64), usize> = self
+            .rolls
+            .iter()
+            .map(|r| (*r, self.neighbours(*r, &offsets)))
+            .collect();
+        let mut queue: VecDeque<(i64, i64)> = self.accessible(rule).into();
         let mut score = 0;
-        while n != 0 {
-            let new_n = self.step();
-            n = new_n;
-            score += n
+
+        while let Some((x, y)) = queue.pop_front() {
+            if !self.rolls.remove(&(x, y)) {
+                continue;
+            }
+            score += 1;
+
+            for (dx, dy) in offsets.iter() {
+                let Some(watcher) = Self::watcher((x, y), (*dx, *dy)) else {
+                    continue;
+                };
+                if !self.rolls.contains(&watcher) {
+                    continue;
+                }
+                let count = counts.get_mut(&watcher).unwrap();
+                *count -= 1;
+                if *count + 1 == rule.threshold {
+                    queue.push_back(watcher);
+                }
+            }
         }
         score
     }
 }
 
-pub fn day_four(path: &str) -> Result<()> {
+// below this share of rolls to cells a set of coordinates takes less room than a
+// bit per cell, as each coordinate is 128 bits and the hash set about doubles that
+const SPARSE_DENSITY: f64 = 1.0 / 256.0;
+
+// a floor of rolls stored whichever way suits it
+#[derive(Debug, PartialEq, Clone)]
+pub enum Floor {
+    Dense(BitGrid),
+    Sparse(SparseGrid),
+}
+
+impl From<&Grid> for Floor {
+    // wrapping needs the edges so it always stays dense
+    fn from(grid: &Grid) -> Self {
+        let cells = grid.grid.len() * grid.grid.first().map_or(0, |r| r.len());
+        let rolls = grid
+            .grid
+            .iter()
+            .flatten()
+            .filter(|t| t == &&Tile::Paper)
+            .count();
+        if !grid.wrap && (rolls as f64) < cells as f64 * SPARSE_DENSITY {
+            return Self::Sparse(SparseGrid::from(grid));
+        }
+        Self::Dense(BitGrid::from(grid))
+    }
+}
+
+impl Floor {
+    // either a grid of tiles or a list of x,y roll coordinates
+    pub fn read(floor_str: &str, wrap: bool) -> std::result::Result<Self, GridError> {
+        if floor_str.contains(',') {
+            if wrap {
+                return Err(GridError::WrapWithoutBounds);
+            }
+            return Ok(Self::Sparse(SparseGrid::read(floor_str)?));
+        }
+        Ok(Self::from(&Grid::read(floor_str)?.with_wrap(wrap)))
+    }
+
+    pub fn score(&self, rule: &RemovalRule) -> u64 {
+        match self {
+            Self::Dense(g) => g.score(rule),
+            Self::Sparse(g) => g.score(rule),
+        }
+    }
+
+    pub fn step(&mut self, rule: &RemovalRule) -> usize {
+        match self {
+            Self::Dense(g) => g.step(rule),
+            Self::Sparse(g) => g.step(rule),
+        }
+    }
+
+    pub fn step_until(&mut self, rule: &RemovalRule) -> usize {
+        match self {
+            Self::Dense(g) => g.step_until(rule),
+            Self::Sparse(g) => g.step_until(rule),
+        }
+    }
+}
+
+pub fn day_four(path: &str, rule: &RemovalRule, show_history: bool, wrap: bool) -> Result<()> {
     let now = Instant::now();
 
     let content = read_to_string(path)?;
 
-    let score = Grid::new(content.as_str()).step_until();
+    let invalid = |e: GridError| io::Error::new(io::ErrorKind::InvalidData, e.to_string());
+
+    let (accessible, removed) = if show_history {
+        // same check Floor::read uses to spot a list of coordinates
+        if content.contains(',') {
+            return Err(invalid(GridError::HistoryWithoutGrid));
+        }
+        let mut grid = Grid::read(content.as_str())
+            .map_err(invalid)?
+            .with_wrap(wrap);
+        let accessible = grid.score(rule);
+        let start = grid.clone();
+        let history = grid.step_until_recorded(rule);
+        for (i, (count, g)) in history
+            .counts()
+            .iter()
+            .zip(history.replay(&start))
+            .enumerate()
+        {
+            println!("round {} removed {}\n{}", i + 1, count, g);
+        }
+        (accessible, history.counts().iter().sum())
+    } else {
+        let mut floor = Floor::read(content.as_str(), wrap).map_err(invalid)?;
+        (floor.score(rule), floor.step_until(rule))
+    };
 
     println!(
-        "score is {}, and it took {}",
-        score,
+        "accessible is {}, removed is {}, and it took {}",
+        accessible,
+        removed,
         now.elapsed().as_micros()
     );
 
@@ -238,6 +1021,7 @@ pub fn day_four(path: &str) -> Result<()> {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::days::test_util::Rng;
 
     #[test]
     fn test_tile_from_char() {
@@ -298,11 +1082,406 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_tile_read() {
+        assert_eq!(Tile::read('.'), Some(Tile::Space));
+        assert_eq!(Tile::read('@'), Some(Tile::Paper));
+        assert_eq!(Tile::read('\r'), None);
+    }
+
+    #[test]
+    fn test_grid_read() {
+        assert_eq!(Grid::read("..@@\r\n@@..\r\n"), Ok(Grid::new("..@@\n@@..")));
+        assert_eq!(
+            Grid::read("..@@\n@@.\n..@@"),
+            Err(GridError::RaggedRow {
+                row: 2,
+                expected: 4,
+                found: 3
+            })
+        );
+        assert_eq!(
+            Grid::read("..@@\n@@x.\n"),
+            Err(GridError::InvalidTile {
+                row: 2,
+                column: 3,
+                found: 'x'
+            })
+        );
+        assert_eq!(
+            Grid::read("..@@\r@@..\n"),
+            Err(GridError::InvalidTile {
+                row: 1,
+                column: 5,
+                found: '\r'
+            })
+        );
+        assert_eq!(Grid::read(""), Err(GridError::Empty));
+        assert_eq!(Grid::read("\n\n"), Err(GridError::Empty));
+    }
+
+    #[test]
+    #[should_panic(expected = "row 2 has 3 tiles but the first row has 4")]
+    fn test_grid_new_panic() {
+        Grid::new("..@@\n@@.");
+    }
+
+    #[test]
+    fn test_grid_neighbours() {
+        let grid = Grid::new("@@@\n@.@\n..@");
+        let moore = Neighbourhood::Moore.offsets();
+        assert_eq!(grid.neighbours(&Position::new(1, 1), &moore, 2, 2), 6);
+        assert_eq!(grid.neighbours(&Position::new(0, 0), &moore, 2, 2), 2);
+        assert_eq!(grid.neighbours(&Position::new(2, 2), &moore, 2, 2), 1);
+
+        let von_neumann = Neighbourhood::VonNeumann.offsets();
+        assert_eq!(grid.neighbours(&Position::new(1, 1), &von_neumann, 2, 2), 3);
+        assert_eq!(grid.neighbours(&Position::new(2, 2), &von_neumann, 2, 2), 1);
+    }
+
+    #[test]
+    fn test_position_offset() {
+        assert_eq!(Position::new(0, 0).offset((-1, 0), 2, 2), None);
+        assert_eq!(Position::new(2, 2).offset((0, 1), 2, 2), None);
+        assert_eq!(
+            Position::new(1, 1).offset((1, -1), 2, 2),
+            Some(Position::new(2, 0))
+        );
+        assert_eq!(
+            Position::new(0, 0).offset((2, 2), 2, 2),
+            Some(Position::new(2, 2))
+        );
+    }
+
+    #[test]
+    fn test_position_wrapping_offset() {
+        assert_eq!(
+            Position::new(0, 0).wrapping_offset((-1, -1), 2, 3),
+            Position::new(2, 3)
+        );
+        assert_eq!(
+            Position::new(2, 3).wrapping_offset((1, 1), 2, 3),
+            Position::new(0, 0)
+        );
+        assert_eq!(
+            Position::new(1, 1).wrapping_offset((1, 0), 2, 3),
+            Position::new(2, 1)
+        );
+
+        // isize::MAX is 1 mod 3 and 3 mod 4, isize::MIN is 1 mod 3 and 0 mod 4
+        assert_eq!(
+            Position::new(1, 1).wrapping_offset((isize::MAX, isize::MAX), 2, 3),
+            Position::new(2, 0)
+        );
+        assert_eq!(
+            Position::new(1, 1).wrapping_offset((isize::MIN, isize::MIN), 2, 3),
+            Position::new(2, 1)
+        );
+    }
+
+    #[test]
+    fn test_grid_wrap() {
+        let input = "@@@@\n@@@@\n@@@@\n@@@@";
+        let rule = RemovalRule::default();
+        assert_eq!(Grid::new(input).score(&rule), 4);
+        assert_eq!(Grid::new(input).with_wrap(true).score(&rule), 0);
+
+        // the corners see each other through the edges
+        let input = "@..@\n....\n....\n@..@";
+        let grid = Grid::new(input).with_wrap(true);
+        let moore = Neighbourhood::Moore.offsets();
+        assert_eq!(grid.neighbours(&Position::new(0, 0), &moore, 3, 3), 3);
+
+        let input = "@@@.\n@@@.\n@@@.\n....";
+        let mut grid = Grid::new(input).with_wrap(true);
+        assert_eq!(grid.step_until(&rule), 9);
+
+        // offsets far bigger than the grid just wrap round, across 4 isize::MAX is one
+        // to the left and down 2 it lands on the other row
+        let rule = RemovalRule::new(
+            1,
+            Neighbourhood::Custom(vec![(isize::MAX, 0), (0, isize::MAX)]),
+        );
+        let mut grid = Grid::new("@@@.\n....").with_wrap(true);
+        let history = grid.clone().step_until_recorded(&rule);
+        assert_eq!(history.counts(), vec![1, 1, 1]);
+        assert_eq!(grid.step_until(&rule), 3);
+    }
+
+    #[test]
+    fn test_bit_grid_from_grid() {
+        let grid = Grid::new("..@@\n@@..\n");
+        let bits = BitGrid::from(&grid);
+        assert_eq!(bits.rows, vec![vec![0b1100], vec![0b0011]]);
+        assert_eq!(Grid::from(&bits), grid);
+
+        let row = "@".repeat(70);
+        let bits = BitGrid::new(&row);
+        assert_eq!(bits.rows, vec![vec![u64::MAX, 0b111111]]);
+    }
+
+    #[test]
+    fn test_bit_grid_shift_into() {
+        let bits = BitGrid::new(&".".repeat(70));
+        let shift = |row: &[u64], dx: isize| {
+            let mut out = vec![0; row.len()];
+            bits.shift_into(row, dx, 0, &mut out);
+            out
+        };
+        let row = vec![1u64 << 63, 1];
+        assert_eq!(shift(&row, 0), row);
+        assert_eq!(shift(&row, 1), vec![0b11 << 62, 0]);
+        assert_eq!(shift(&row, -1), vec![0, 0b11]);
+        assert_eq!(shift(&row, 63), vec![0b11, 0]);
+        assert_eq!(shift(&row, -6), vec![0, 0b100000]);
+        // nothing spills past the width
+        assert_eq!(shift(&[0, 0b100000], -1), vec![0, 0]);
+
+        let mut out = vec![0];
+        bits.shift_into(&row, -1, 1, &mut out);
+        assert_eq!(out, vec![0b11]);
+    }
+
+    #[test]
+    fn test_bit_grid_matches_grid() {
+        let input = "..@@.@@@@.\n@@@.@.@.@@\n@@@@@.@.@@\n@.@@@@..@.\n@@.@@@@.@@\n.@@@@@@@.@\n.@.@.@.@@@\n@.@@@.@@@@\n.@@@@@@@@.\n@.@.@@@.@.";
+        let mut bits = BitGrid::new(input);
+        assert_eq!(bits.score(&RemovalRule::default()), 13);
+        assert_eq!(bits.step_until(&RemovalRule::default()), 43);
+
+        let mut rng = Rng::new(0x9e3779b97f4a7c15);
+        let mut next = || rng.next_u64();
+        let rules = [
+            RemovalRule::default(),
+            RemovalRule::new(2, Neighbourhood::VonNeumann),
+            RemovalRule::new(5, Neighbourhood::Moore),
+            RemovalRule::new(2, Neighbourhood::Custom(vec![(70, 0), (-1, 2), (3, -1)])),
+            RemovalRule::new(
+                2,
+                Neighbourhood::Custom(vec![
+                    (1, isize::MAX),
+                    (isize::MIN, 0),
+                    (isize::MAX, -1),
+                    (0, isize::MIN),
+                    (-1, 0),
+                    (0, 1),
+                ]),
+            ),
+        ];
+
+        for _ in 0..20 {
+            let (width, height) = ((next() % 150) as usize + 1, (next() % 12) as usize + 1);
+            let input: Vec<String> = (0..height)
+                .map(|_| {
+                    (0..width)
+                        .map(|_| if next() % 3 == 0 { '.' } else { '@' })
+                        .collect()
+                })
+                .collect();
+            let input = input.join("\n");
+
+            for rule in rules.iter() {
+                for wrap in [false, true] {
+                    let mut grid = Grid::new(&input).with_wrap(wrap);
+                    let mut bits = BitGrid::new(&input).with_wrap(wrap);
+                    assert_eq!(bits.score(rule), grid.score(rule));
+                    assert_eq!(bits.step(rule), grid.step(rule));
+                    assert_eq!(Grid::from(&bits), grid);
+                    assert_eq!(bits.step_until(rule), grid.step_until(rule));
+                    assert_eq!(Grid::from(&bits), grid);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_sparse_grid_read() {
+        let grid = SparseGrid::read("0,0\n-5, 3\n\n1000000000000,-1\n").unwrap();
+        assert_eq!(
+            grid,
+            SparseGrid::new(HashSet::from([(0, 0), (-5, 3), (1000000000000, -1)]))
+        );
+        assert_eq!(
+            SparseGrid::read("0,0\n1;2"),
+            Err(GridError::InvalidCoordinate { row: 2 })
+        );
+        assert_eq!(
+            SparseGrid::read("a,2"),
+            Err(GridError::InvalidCoordinate { row: 1 })
+        );
+    }
+
+    #[test]
+    fn test_sparse_grid_far_apart() {
+        // two 3x3 blocks a long way apart, one of them at negative coordinates
+        let mut rolls = HashSet::new();
+        for i in 0..3 {
+            for j in 0..3 {
+                rolls.insert((i - 1_000_000_000, j - 7));
+                rolls.insert((i + 1_000_000_000, j + 5_000_000_000));
+            }
+        }
+        let rule = RemovalRule::default();
+        let mut grid = SparseGrid::new(rolls);
+        assert_eq!(grid.score(&rule), 8);
+        assert_eq!(grid.clone().step(&rule), 8);
+        assert_eq!(grid.step_until(&rule), 18);
+        assert!(grid.rolls.is_empty());
+    }
+
+    #[test]
+    fn test_sparse_grid_edges_of_i64() {
+        let rule = RemovalRule::default();
+        assert_eq!(
+            SparseGrid::read("9223372036854775807,0")
+                .unwrap()
+                .score(&rule),
+            1
+        );
+
+        // 3x3 blocks in the far corners, the offsets off the edge just find nothing
+        let mut rolls = HashSet::new();
+        for i in 0..3 {
+            for j in 0..3 {
+                rolls.insert((i64::MAX - i, i64::MAX - j));
+                rolls.insert((i64::MIN + i, i64::MIN + j));
+            }
+        }
+        let mut grid = SparseGrid::new(rolls);
+        assert_eq!(grid.score(&rule), 8);
+        assert_eq!(grid.clone().step(&rule), 8);
+        assert_eq!(grid.step_until(&rule), 18);
+        assert!(grid.rolls.is_empty());
+    }
+
+    #[test]
+    fn test_sparse_grid_matches_grid() {
+        let input = "..@@.@@@@.\n@@@.@.@.@@\n@@@@@.@.@@\n@.@@@@..@.\n@@.@@@@.@@\n.@@@@@@@.@\n.@.@.@.@@@\n@.@@@.@@@@\n.@@@@@@@@.\n@.@.@@@.@.";
+        let rules = [
+            RemovalRule::default(),
+            RemovalRule::new(2, Neighbourhood::VonNeumann),
+            RemovalRule::new(2, Neighbourhood::Custom(vec![(1, 0), (-1, 2)])),
+        ];
+        for rule in rules.iter() {
+            let mut grid = Grid::new(input);
+            let mut sparse = SparseGrid::from(&grid);
+            assert_eq!(sparse.score(rule), grid.score(rule));
+            assert_eq!(sparse.step(rule), grid.step(rule));
+            assert_eq!(sparse, SparseGrid::from(&grid));
+            assert_eq!(sparse.step_until(rule), grid.step_until(rule));
+            assert_eq!(sparse, SparseGrid::from(&grid));
+        }
+    }
+
+    #[test]
+    fn test_floor_picks_backend() {
+        let input = "..@@.@@@@.\n@@@.@.@.@@\n@@@@@.@.@@";
+        assert!(matches!(Floor::read(input, false), Ok(Floor::Dense(_))));
+
+        let mut empty = vec![".".repeat(100); 100];
+        empty[50].replace_range(50..53, "@@@");
+        let input = empty.join("\n");
+        let mut floor = Floor::read(&input, false).unwrap();
+        assert!(matches!(floor, Floor::Sparse(_)));
+        assert_eq!(floor.score(&RemovalRule::default()), 3);
+        assert_eq!(floor.step_until(&RemovalRule::default()), 3);
+
+        assert!(matches!(Floor::read(&input, true), Ok(Floor::Dense(_))));
+        assert!(matches!(
+            Floor::read("1,2\n3,4", false),
+            Ok(Floor::Sparse(_))
+        ));
+        assert_eq!(
+            Floor::read("1,2\n3,4", true),
+            Err(GridError::WrapWithoutBounds)
+        );
+    }
+
+    #[test]
+    fn test_neighbourhood_read() {
+        assert_eq!(Neighbourhood::read("moore"), Some(Neighbourhood::Moore));
+        assert_eq!(
+            Neighbourhood::read("von-neumann"),
+            Some(Neighbourhood::VonNeumann)
+        );
+        assert_eq!(
+            Neighbourhood::read("1,0;-1, 2"),
+            Some(Neighbourhood::Custom(vec![(1, 0), (-1, 2)]))
+        );
+        assert_eq!(Neighbourhood::read("1;0"), None);
+        assert_eq!(Neighbourhood::read("up"), None);
+    }
+
+    #[test]
+    fn test_grid_step_until_recorded() {
+        let input = "..@@.@@@@.\n@@@.@.@.@@\n@@@@@.@.@@\n@.@@@@..@.\n@@.@@@@.@@\n.@@@@@@@.@\n.@.@.@.@@@\n@.@@@.@@@@\n.@@@@@@@@.\n@.@.@@@.@.";
+        let rule = RemovalRule::default();
+
+        let mut grid = Grid::new(input);
+        let history = grid.step_until_recorded(&rule);
+        assert_eq!(history.counts(), vec![13, 12, 7, 5, 2, 1, 1, 1, 1]);
+
+        assert_eq!(history.round_of(2, 0), Some(1));
+        assert_eq!(history.round_of(0, 0), None);
+        assert_eq!(history.round_of(4, 4), None);
+
+        let mut stepped = Grid::new(input);
+        let replayed = history.replay(&Grid::new(input));
+        assert_eq!(replayed.len(), 9);
+        for g in replayed.iter() {
+            stepped.step(&rule);
+            assert_eq!(g, &stepped);
+        }
+        assert_eq!(replayed.last().unwrap(), &grid);
+    }
+
+    #[test]
+    fn test_grid_display() {
+        let input = "..@@\n@@..\n";
+        assert_eq!(Grid::new(input).to_string(), input);
+    }
+
+    #[test]
+    fn test_grid_rules() {
+        let input = "..@@.@@@@.\n@@@.@.@.@@\n@@@@@.@.@@\n@.@@@@..@.\n@@.@@@@.@@\n.@@@@@@@.@\n.@.@.@.@@@\n@.@@@.@@@@\n.@@@@@@@@.\n@.@.@@@.@.";
+
+        // nothing has fewer than 0 neighbours and everything has fewer than 9
+        assert_eq!(
+            Grid::new(input).score(&RemovalRule::new(0, Neighbourhood::Moore)),
+            0
+        );
+        let mut grid = Grid::new(input);
+        assert_eq!(
+            grid.step_until(&RemovalRule::new(9, Neighbourhood::Moore)),
+            71
+        );
+
+        let rule = RemovalRule::new(2, Neighbourhood::VonNeumann);
+        let mut stepped = Grid::new(input);
+        let mut n = 0;
+        loop {
+            let removed = stepped.step(&rule);
+            if removed == 0 {
+                break;
+            }
+            n += removed;
+        }
+        let mut grid = Grid::new(input);
+        assert_eq!(grid.step_until(&rule), n);
+        assert_eq!(grid, stepped);
+
+        // only looking right, so each roll is removed once the roll to its right is gone
+        let rule = RemovalRule::new(1, Neighbourhood::Custom(vec![(1, 0)]));
+        let mut grid = Grid::new("@@@.\n.@@@");
+        assert_eq!(grid.score(&rule), 2);
+        assert_eq!(grid.step_until(&rule), 6);
+    }
+
     #[test]
     fn test_grid_score() {
         let input = "..@@.@@@@.\n@@@.@.@.@@\n@@@@@.@.@@\n@.@@@@..@.\n@@.@@@@.@@\n.@@@@@@@.@\n.@.@.@.@@@\n@.@@@.@@@@\n.@@@@@@@@.\n@.@.@@@.@.";
 
-        assert_eq!(Grid::new(input).score(), 13)
+        assert_eq!(Grid::new(input).score(&RemovalRule::default()), 13)
     }
 
     #[test]
@@ -312,7 +1491,7 @@ mod test {
         let expected = ".......@..\n.@@.@.@.@@\n@@@@@...@@\n@.@@@@..@.\n.@.@@@@.@.\n.@@@@@@@.@\n.@.@.@.@@@\n..@@@.@@@@\n.@@@@@@@@.\n....@@@...";
 
         let mut actual = Grid::new(input);
-        let n = actual.step();
+        let n = actual.step(&RemovalRule::default());
         assert_eq!(n, 13);
         assert_eq!(actual, Grid::new(expected));
     }
@@ -325,10 +1504,54 @@ mod test {
 
         let mut grid = Grid::new(input);
 
-        let n = grid.step_until();
+        let n = grid.step_until(&RemovalRule::default());
 
         assert_eq!(n, 43);
 
         assert_eq!(grid, Grid::new(expected));
     }
+
+    #[test]
+    fn test_grid_step_until_matches_step() {
+        let input = "@@@@@@\n@@@@@@\n@@.@@@\n@@@@@@\n.@@@@@\n@@@@@.";
+
+        let mut stepped = Grid::new(input);
+        let mut n = 0;
+        loop {
+            let removed = stepped.step(&RemovalRule::default());
+            if removed == 0 {
+                break;
+            }
+            n += removed;
+        }
+
+        let mut grid = Grid::new(input);
+        assert_eq!(grid.step_until(&RemovalRule::default()), n);
+        assert_eq!(grid, stepped);
+    }
+
+    #[test]
+    fn test_grid_step_until_extreme_offsets() {
+        let input = "@@@@@@\n@@@@@@\n@@.@@@\n@@@@@@\n.@@@@@\n@@@@@.";
+        let rule = RemovalRule::new(
+            2,
+            Neighbourhood::Custom(vec![(isize::MIN, 0), (0, isize::MIN), (1, 0), (0, 1)]),
+        );
+
+        let mut stepped = Grid::new(input);
+        let mut n = 0;
+        loop {
+            let removed = stepped.step(&rule);
+            if removed == 0 {
+                break;
+            }
+            n += removed;
+        }
+
+        let mut grid = Grid::new(input);
+        let history = grid.clone().step_until_recorded(&rule);
+        assert_eq!(history.counts().iter().sum::<usize>(), n);
+        assert_eq!(grid.step_until(&rule), n);
+        assert_eq!(grid, stepped);
+    }
 }
@@ -1,7 +1,9 @@
 use std::{
     cmp::{max, min},
+    collections::{BTreeMap, HashMap},
+    fmt,
     fs::read_to_string,
-    io::Result,
+    io::{self, Result},
     time::Instant,
 };
 
@@ -33,40 +35,139 @@ impl Ranges {
         score
     }
 
+    // the rest of these expect combine to have been called, so the ranges are sorted
+    // and disjoint
+
+    // binary search for the first range that doesnt end before id
+    pub fn contains(&self, id: u64) -> bool {
+        let i = self.ranges.partition_point(|r| r.end < id);
+        self.ranges.get(i).is_some_and(|r| r.start <= id)
+    }
+
+    // sort the ids then walk them and the ranges together, each only moves forwards
+    pub fn count_fresh(&self, items: &[Item]) -> u64 {
+        let mut ids: Vec<u64> = items.iter().map(|i| i.id).collect();
+        ids.sort_unstable();
+
+        let mut ranges = self.ranges.iter().peekable();
+        let mut count = 0;
+        for id in ids {
+            while ranges.next_if(|r| r.end < id).is_some() {}
+            match ranges.peek() {
+                Some(r) if r.start <= id => count += 1,
+                Some(_) => {}
+                None => break,
+            }
+        }
+        count
+    }
+
+    // sort by start then sweep once, folding each range into the last merged one when
+    // they overlap or touch (3-5 and 6-8 become 3-8). leaves the ranges sorted and disjoint
     pub fn combine(&mut self) {
-        // we want to have the simplest repr
-        // want to pairwise combine them from the left
-        //
-
-        let mut buffer = vec![self.ranges.first().unwrap().to_owned()];
-
-        self.ranges.iter().skip(1).for_each(|r| {
-            // from the left of the buffer i want to combine with r and take that result and
-            // combine the nect elemeent of the buffer
-            //
-            // so i need to go to
-            let mut r_clone = r.clone();
-            let mut local = vec![];
-            for b in buffer.iter() {
-                // everything in buffer is always exclusive
-                let combined = r_clone.combine(b);
-                if combined.len() == 1 {
-                    // r combined with b to make a new extended range
-                    // so now for the next b we continue with r+b
-                    r_clone = combined.first().unwrap().to_owned();
-                } else {
-                    // r and b are exlusive so now for the next b we continue with r
-                    local.push(b.to_owned())
+        self.ranges.sort_by_key(|r| r.start);
+
+        let mut buffer: Vec<Range> = Vec::with_capacity(self.ranges.len());
+        for r in self.ranges.drain(..) {
+            match buffer.last_mut() {
+                Some(last) if r.start <= last.end.saturating_add(1) => {
+                    last.end = max(last.end, r.end);
                 }
+                _ => buffer.push(r),
             }
-            // this is now all of the exclusive bs and the combined r+bs
-            local.push(r_clone);
-            local.sort_by(|a, b| a.start.cmp(&b.start));
-            buffer = local;
-        });
+        }
 
         self.ranges = buffer;
     }
+
+    // a combined copy, so the set operations work whatever state the inputs are in
+    fn combined(&self) -> Self {
+        let mut c = Self::new(self.ranges.clone());
+        c.combine();
+        c
+    }
+
+    // how many ids are covered, u128 as the whole u64 range has one more id than fits
+    pub fn len(&self) -> u128 {
+        self.combined()
+            .ranges
+            .iter()
+            .map(|r| r.interval().len().unwrap())
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ranges.is_empty()
+    }
+
+    // the ids missing between one range and the next
+    pub fn gaps(&self) -> impl Iterator<Item = Range> {
+        let ranges = self.combined().ranges;
+        (1..ranges.len()).map(move |i| Range::new(ranges[i - 1].end + 1, ranges[i].start - 1))
+    }
+
+    pub fn union(&self, another: &Self) -> Self {
+        let mut u = Self::new([self.ranges.clone(), another.ranges.clone()].concat());
+        u.combine();
+        u
+    }
+
+    // walk both together, the range that ends first can't overlap anything later
+    pub fn intersection(&self, another: &Self) -> Self {
+        let (a, b) = (self.combined().ranges, another.combined().ranges);
+        let (mut i, mut j) = (0, 0);
+        let mut result = vec![];
+        while i < a.len() && j < b.len() {
+            let (start, end) = (max(a[i].start, b[j].start), min(a[i].end, b[j].end));
+            if start <= end {
+                result.push(Range::new(start, end));
+            }
+            if a[i].end < b[j].end {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        Self::new(result)
+    }
+
+    // every id within bounds that isnt covered
+    pub fn complement(&self, bounds: &Range) -> Self {
+        let mut result = vec![];
+        // the next id that could start a gap, None once we have gone past u64::MAX
+        let mut cursor = Some(bounds.start);
+        for r in self.combined().ranges.iter() {
+            let Some(c) = cursor else {
+                break;
+            };
+            if r.end < c {
+                continue;
+            }
+            if r.start > bounds.end {
+                break;
+            }
+            if r.start > c {
+                result.push(Range::new(c, r.start - 1));
+            }
+            cursor = r.end.checked_add(1);
+        }
+        if let Some(c) = cursor
+            && c <= bounds.end
+        {
+            result.push(Range::new(c, bounds.end));
+        }
+        Self::new(result)
+    }
+
+    // ids in self but not in another
+    pub fn difference(&self, another: &Self) -> Self {
+        self.intersection(&another.complement(&Range::new(0, u64::MAX)))
+    }
+
+    // ids in exactly one of them
+    pub fn symmetric_difference(&self, another: &Self) -> Self {
+        self.union(another).difference(&self.intersection(another))
+    }
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -79,6 +180,11 @@ impl Range {
     pub fn new(start: u64, end: u64) -> Self {
         Self { start, end }
     }
+
+    pub fn interval(&self) -> Interval<u64> {
+        Interval::inclusive(self.start, self.end)
+    }
+
     pub fn is_item_fresh(&self, item: &Item) -> bool {
         item.id <= self.end && item.id >= self.start
     }
@@ -94,11 +200,332 @@ impl Range {
             return new;
         };
         let mut new = vec![self.clone(), another.clone()];
-        new.sort_by(|a, b| a.start.cmp(&b.start));
+        new.sort_by_key(|r| r.start);
         new
     }
 }
 
+// the original ranges are kept in a treap ordered by (start, handle), where each node
+// also keeps the largest end in its subtree, so insert and remove are expected
+// O(log n) and a stab skips any subtree that ends before the id or starts after it,
+// O(log n) per range it finds. next to it the merged blocks are kept up to date as
+// ranges come and go. an insert swallows the blocks it touches, and a remove only
+// recovers the part of its block it covered, by asking the treap how far the ranges
+// starting before each point reach, so it costs O(log n) per original starting in
+// the removed range rather than the whole block
+#[derive(Debug, Default)]
+pub struct IntervalTree {
+    next_handle: usize,
+    starts: HashMap<usize, u64>, // handle -> start
+    root: Link,
+    merged: BTreeMap<u64, u64>, // start -> end, disjoint and not touching
+}
+
+type Link = Option<Box<Node>>;
+
+#[derive(Debug)]
+struct Node {
+    key: (u64, usize), // (start, handle)
+    end: u64,
+    priority: u64,
+    max_end: u64,
+    left: Link,
+    right: Link,
+}
+
+impl Node {
+    // splitmix64 of the handle, any well spread priority keeps the treap balanced
+    fn new(range: &Range, handle: usize) -> Box<Self> {
+        let mut z = (handle as u64).wrapping_add(0x9e3779b97f4a7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        Box::new(Self {
+            key: (range.start, handle),
+            end: range.end,
+            priority: z ^ (z >> 31),
+            max_end: range.end,
+            left: None,
+            right: None,
+        })
+    }
+
+    fn update(&mut self) {
+        self.max_end = [&self.left, &self.right]
+            .iter()
+            .filter_map(|l| l.as_ref().map(|n| n.max_end))
+            .fold(self.end, max);
+    }
+
+    fn range(&self) -> Range {
+        Range::new(self.key.0, self.end)
+    }
+}
+
+// everything keyed below key goes left, the rest right
+fn split(link: Link, key: (u64, usize)) -> (Link, Link) {
+    let Some(mut n) = link else {
+        return (None, None);
+    };
+    if n.key < key {
+        let (l, r) = split(n.right.take(), key);
+        n.right = l;
+        n.update();
+        (Some(n), r)
+    } else {
+        let (l, r) = split(n.left.take(), key);
+        n.left = r;
+        n.update();
+        (l, Some(n))
+    }
+}
+
+// every key in a has to be below every key in b
+fn join(a: Link, b: Link) -> Link {
+    match (a, b) {
+        (None, b) => b,
+        (a, None) => a,
+        (Some(mut a), Some(mut b)) => {
+            if a.priority > b.priority {
+                a.right = join(a.right.take(), Some(b));
+                a.update();
+                Some(a)
+            } else {
+                b.left = join(Some(a), b.left.take());
+                b.update();
+                Some(b)
+            }
+        }
+    }
+}
+
+fn reaches(link: &Link, id: u64) -> bool {
+    link.as_ref().is_some_and(|n| n.max_end >= id)
+}
+
+// the furthest end of any range starting at or before id
+fn reach(link: &Link, id: u64) -> Option<u64> {
+    let n = link.as_ref()?;
+    if n.key.0 > id {
+        return reach(&n.left, id);
+    }
+    let here = n.left.as_ref().map_or(n.end, |l| max(l.max_end, n.end));
+    Some(reach(&n.right, id).map_or(here, |r| max(r, here)))
+}
+
+// the first start after id
+fn next_start(link: &Link, id: u64) -> Option<u64> {
+    let n = link.as_ref()?;
+    if n.key.0 <= id {
+        return next_start(&n.right, id);
+    }
+    Some(next_start(&n.left, id).unwrap_or(n.key.0))
+}
+
+fn stab(link: &Link, id: u64, hits: &mut Vec<(usize, Range)>) {
+    let Some(n) = link else {
+        return;
+    };
+    if reaches(&n.left, id) {
+        stab(&n.left, id, hits);
+    }
+    if n.key.0 > id {
+        return;
+    }
+    if n.end >= id {
+        hits.push((n.key.1, n.range()));
+    }
+    if reaches(&n.right, id) {
+        stab(&n.right, id, hits);
+    }
+}
+
+impl IntervalTree {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // returns the handle to remove it with later
+    pub fn insert(&mut self, range: Range) -> usize {
+        let handle = self.next_handle;
+        self.next_handle += 1;
+        self.starts.insert(handle, range.start);
+
+        let (l, r) = split(self.root.take(), (range.start, handle));
+        self.root = join(join(l, Some(Node::new(&range, handle))), r);
+
+        // swallow every block that overlaps or touches the new range
+        let (mut start, mut end) = (range.start, range.end);
+        let touching: Vec<(u64, u64)> = self
+            .merged
+            .range(..=range.end.saturating_add(1))
+            .rev()
+            .take_while(|(_, e)| e.saturating_add(1) >= range.start)
+            .map(|(s, e)| (*s, *e))
+            .collect();
+        for (s, e) in touching {
+            self.merged.remove(&s);
+            start = min(start, s);
+            end = max(end, e);
+        }
+        self.merged.insert(start, end);
+        handle
+    }
+
+    pub fn remove(&mut self, handle: usize) -> Option<Range> {
+        let start = self.starts.remove(&handle)?;
+        let (l, r) = split(self.root.take(), (start, handle));
+        let (node, r) = split(r, (start, handle + 1));
+        self.root = join(l, r);
+        let range = node?.range();
+
+        // the rest of the block is still covered by whatever covered it before, so only
+        // the removed range needs working out again
+        let (block_start, block_end) = self.block(range.start)?;
+        self.merged.remove(&block_start);
+        let mut pieces = vec![];
+        if block_start < range.start {
+            pieces.push(Range::new(block_start, range.start - 1));
+        }
+        let mut id = Some(range.start);
+        while let Some(x) = id.filter(|x| *x <= range.end) {
+            match reach(&self.root, x).filter(|r| *r >= x) {
+                Some(r) => {
+                    pieces.push(Range::new(x, min(r, range.end)));
+                    id = r.checked_add(1);
+                }
+                None => id = next_start(&self.root, x),
+            }
+        }
+        if range.end < block_end {
+            pieces.push(Range::new(range.end + 1, block_end));
+        }
+
+        let mut rebuilt = Ranges::new(pieces);
+        rebuilt.combine();
+        for r in rebuilt.ranges {
+            self.merged.insert(r.start, r.end);
+        }
+        Some(range)
+    }
+
+    fn block(&self, id: u64) -> Option<(u64, u64)> {
+        self.merged
+            .range(..=id)
+            .next_back()
+            .filter(|(_, e)| **e >= id)
+            .map(|(s, e)| (*s, *e))
+    }
+
+    pub fn contains(&self, id: u64) -> bool {
+        self.block(id).is_some()
+    }
+
+    // the original ranges that make id fresh with their handles, ordered by start
+    pub fn stab(&self, id: u64) -> Vec<(usize, Range)> {
+        let mut hits = vec![];
+        if reaches(&self.root, id) {
+            stab(&self.root, id, &mut hits);
+        }
+        hits
+    }
+
+    pub fn ranges(&self) -> Ranges {
+        Ranges::new(
+            self.merged
+                .iter()
+                .map(|(s, e)| Range::new(*s, *e))
+                .collect(),
+        )
+    }
+}
+
+// the primitive integers an Interval can be over
+pub trait Integer: Copy + Ord + fmt::Debug {
+    // maps onto u128 keeping the order, so the distance between two values can
+    // always be worked out without overflowing
+    fn to_ordered(self) -> u128;
+    fn checked_pred(self) -> Option<Self>;
+}
+
+macro_rules! unsigned_integer {
+    ($($t:ty),*) => {$(
+        impl Integer for $t {
+            fn to_ordered(self) -> u128 {
+                self as u128
+            }
+            fn checked_pred(self) -> Option<Self> {
+                self.checked_sub(1)
+            }
+        }
+    )*};
+}
+
+macro_rules! signed_integer {
+    ($($t:ty),*) => {$(
+        impl Integer for $t {
+            // flipping the sign bit puts i128::MIN at 0 and i128::MAX at u128::MAX
+            fn to_ordered(self) -> u128 {
+                (self as i128 as u128) ^ (1 << 127)
+            }
+            fn checked_pred(self) -> Option<Self> {
+                self.checked_sub(1)
+            }
+        }
+    )*};
+}
+
+unsigned_integer!(u8, u16, u32, u64, u128, usize);
+signed_integer!(i8, i16, i32, i64, i128, isize);
+
+// a run of integers stored as inclusive bounds, None when it holds nothing
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Interval<T: Integer> {
+    bounds: Option<(T, T)>,
+}
+
+impl<T: Integer> Interval<T> {
+    // start..=end, empty if end is before start
+    pub fn inclusive(start: T, end: T) -> Self {
+        Self {
+            bounds: (start <= end).then_some((start, end)),
+        }
+    }
+
+    // start..end, empty if end isnt after start
+    pub fn half_open(start: T, end: T) -> Self {
+        match end.checked_pred() {
+            Some(last) if start < end => Self::inclusive(start, last),
+            _ => Self { bounds: None },
+        }
+    }
+
+    pub fn start(&self) -> Option<T> {
+        self.bounds.map(|(s, _)| s)
+    }
+
+    // the last value in the interval
+    pub fn end(&self) -> Option<T> {
+        self.bounds.map(|(_, e)| e)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bounds.is_none()
+    }
+
+    pub fn contains(&self, value: T) -> bool {
+        self.bounds.is_some_and(|(s, e)| s <= value && value <= e)
+    }
+
+    // how many values it holds. this only fails for the whole of u128 or i128, which
+    // holds one more value than a u128 can count
+    pub fn len(&self) -> Option<u128> {
+        match self.bounds {
+            None => Some(0),
+            Some((s, e)) => (e.to_ordered() - s.to_ordered()).checked_add(1),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq)]
 pub struct Item {
     id: u64,
@@ -110,30 +537,104 @@ impl Item {
     }
 }
 
-pub fn day_five(path: &str) -> Result<()> {
-    let content = read_to_string(path)?;
-    let mut content_split = content.split("\n\n");
+// lines are counted from 1 to match an editor
+#[derive(Debug, PartialEq)]
+pub enum InputError {
+    MalformedRange { line: usize },
+    InvertedRange { line: usize, start: u64, end: u64 },
+    DuplicateRange { line: usize, first_line: usize },
+    MalformedItem { line: usize },
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MalformedRange { line } => write!(f, "line {}: expected start-end", line),
+            Self::InvertedRange { line, start, end } => write!(
+                f,
+                "line {}: range {}-{} ends before it starts",
+                line, start, end
+            ),
+            Self::DuplicateRange { line, first_line } => write!(
+                f,
+                "line {}: range is the same as the one on line {}",
+                line, first_line
+            ),
+            Self::MalformedItem { line } => write!(f, "line {}: expected an item id", line),
+        }
+    }
+}
 
-    let ranges = content_split.next().unwrap();
+// the fresh ranges, a blank line, then the available item ids. lines() takes care
+// of \r\n and any blank lines at the end are ignored. every problem found is
+// returned rather than just the first, and with swap_inverted a range like 20-10 is
+// read as 10-20 instead of being an error. a repeated range doesn't change the
+// answer so it is kept and only reported back as a warning alongside the input
+pub fn read_input(
+    input: &str,
+    swap_inverted: bool,
+) -> std::result::Result<(Ranges, Vec<Item>, Vec<InputError>), Vec<InputError>> {
+    let mut lines = input.lines().enumerate().map(|(i, l)| (i + 1, l.trim()));
+    let mut errors = vec![];
+    let mut warnings = vec![];
 
-    let ranges: Vec<Range> = ranges
-        .split("\n")
-        .map(|r| {
-            let mut nums = r.split("-");
-            let start = nums.next().unwrap().parse().unwrap();
-            let end = nums.next().unwrap().parse().unwrap();
-            Range::new(start, end)
-        })
-        .collect();
+    let mut seen: HashMap<(u64, u64), usize> = HashMap::new();
+    let mut ranges: Vec<Range> = vec![];
+    for (line, r) in lines.by_ref().take_while(|(_, l)| !l.is_empty()) {
+        let Some((Ok(mut start), Ok(mut end))) = r
+            .split_once('-')
+            .map(|(s, e)| (s.trim().parse::<u64>(), e.trim().parse::<u64>()))
+        else {
+            errors.push(InputError::MalformedRange { line });
+            continue;
+        };
+        if start > end {
+            if !swap_inverted {
+                errors.push(InputError::InvertedRange { line, start, end });
+                continue;
+            }
+            (start, end) = (end, start);
+        }
+        let first_line = *seen.entry((start, end)).or_insert(line);
+        if first_line != line {
+            warnings.push(InputError::DuplicateRange { line, first_line });
+        }
+        ranges.push(Range::new(start, end));
+    }
+
+    let mut items: Vec<Item> = vec![];
+    for (line, l) in lines.filter(|(_, l)| !l.is_empty()) {
+        match l.parse() {
+            Ok(id) => items.push(Item::new(id)),
+            Err(_) => errors.push(InputError::MalformedItem { line }),
+        }
+    }
+
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+    Ok((Ranges::new(ranges), items, warnings))
+}
+
+pub fn day_five(path: &str, swap_inverted: bool) -> Result<()> {
+    let content = read_to_string(path)?;
 
     let now = Instant::now();
-    let mut r = Ranges::new(ranges);
+    let (mut r, items, warnings) = read_input(&content, swap_inverted).map_err(|errors| {
+        let report: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+        io::Error::new(io::ErrorKind::InvalidData, report.join("\n"))
+    })?;
+    for w in warnings.iter() {
+        println!("warning: {}", w);
+    }
     r.combine();
 
-    let score: u64 = r.ranges.iter().map(|r| (r.end - r.start) + 1).sum();
+    let fresh = r.count_fresh(&items);
+    let score = r.len();
 
     println!(
-        "todays score is {}, and took {}",
+        "fresh items is {}, fresh ids is {}, and took {}",
+        fresh,
         score,
         now.elapsed().as_micros()
     );
@@ -144,6 +645,7 @@ pub fn day_five(path: &str) -> Result<()> {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::days::test_util::Rng;
 
     #[test]
     fn test_range_new() {
@@ -202,6 +704,92 @@ mod test {
         )
     }
 
+    #[test]
+    fn test_read_input() {
+        let expected = || {
+            (
+                Ranges::new(vec![Range::new(3, 5), Range::new(10, 14)]),
+                vec![Item::new(1), Item::new(5)],
+                vec![],
+            )
+        };
+        assert_eq!(read_input("3-5\n10-14\n\n1\n5", false), Ok(expected()));
+        assert_eq!(
+            read_input("3-5\r\n10-14\r\n\r\n1\r\n5\r\n\r\n", false),
+            Ok(expected())
+        );
+        assert_eq!(
+            read_input("3-5\n10-14\n\n1\n5\n\n\n", false),
+            Ok(expected())
+        );
+
+        assert_eq!(
+            read_input("3-5\n", false),
+            Ok((Ranges::new(vec![Range::new(3, 5)]), vec![], vec![]))
+        );
+
+        // duplicates are kept and reported as warnings
+        assert_eq!(
+            read_input("3-5\n1-2\n3-5\n3-5\n\n4", false),
+            Ok((
+                ranges(&[(3, 5), (1, 2), (3, 5), (3, 5)]),
+                vec![Item::new(4)],
+                vec![
+                    InputError::DuplicateRange {
+                        line: 3,
+                        first_line: 1
+                    },
+                    InputError::DuplicateRange {
+                        line: 4,
+                        first_line: 1
+                    },
+                ]
+            ))
+        );
+    }
+
+    #[test]
+    fn test_read_input_errors() {
+        let input = "3-5\n20-10\nten-12\n3-5\n7\n\n1\nx\n";
+        assert_eq!(
+            read_input(input, false),
+            Err(vec![
+                InputError::InvertedRange {
+                    line: 2,
+                    start: 20,
+                    end: 10
+                },
+                InputError::MalformedRange { line: 3 },
+                InputError::MalformedRange { line: 5 },
+                InputError::MalformedItem { line: 8 },
+            ])
+        );
+    }
+
+    #[test]
+    fn test_read_input_swap_inverted() {
+        assert_eq!(
+            read_input("20-10\n3-5\n\n12", true),
+            Ok((
+                Ranges::new(vec![Range::new(10, 20), Range::new(3, 5)]),
+                vec![Item::new(12)],
+                vec![]
+            ))
+        );
+        // once swapped it can be a duplicate of an earlier range
+        assert_eq!(
+            read_input("10-20\n20-10\n", true),
+            Ok((
+                ranges(&[(10, 20), (10, 20)]),
+                vec![],
+                vec![InputError::DuplicateRange {
+                    line: 2,
+                    first_line: 1
+                }]
+            ))
+        );
+    }
+
     #[test]
     fn test_score() {
         let ranges = vec![
@@ -237,4 +825,343 @@ mod test {
 
         assert_eq!(actual, expected);
     }
+
+    #[test]
+    fn test_contains() {
+        let mut ranges = Ranges::new(vec![
+            Range::new(3, 5),
+            Range::new(10, 14),
+            Range::new(16, 20),
+            Range::new(12, 18),
+        ]);
+        ranges.combine();
+
+        for (id, fresh) in [
+            (0, false),
+            (3, true),
+            (5, true),
+            (6, false),
+            (9, false),
+            (10, true),
+            (17, true),
+            (20, true),
+            (21, false),
+            (u64::MAX, false),
+        ] {
+            assert_eq!(ranges.contains(id), fresh, "id {}", id);
+        }
+        assert!(!Ranges::new(vec![]).contains(1));
+    }
+
+    #[test]
+    fn test_count_fresh() {
+        let mut ranges = Ranges::new(vec![
+            Range::new(3, 5),
+            Range::new(10, 14),
+            Range::new(16, 20),
+            Range::new(12, 18),
+        ]);
+        ranges.combine();
+
+        let items: Vec<Item> = [32, 17, 1, 11, 5, 8, 5, 20, 21]
+            .into_iter()
+            .map(Item::new)
+            .collect();
+        assert_eq!(ranges.count_fresh(&items), 5);
+        assert_eq!(
+            ranges.count_fresh(&items),
+            items.iter().filter(|i| ranges.contains(i.id)).count() as u64
+        );
+        assert_eq!(ranges.count_fresh(&[]), 0);
+        assert_eq!(Ranges::new(vec![]).count_fresh(&items), 0);
+    }
+
+    fn ranges(pairs: &[(u64, u64)]) -> Ranges {
+        Ranges::new(pairs.iter().map(|(s, e)| Range::new(*s, *e)).collect())
+    }
+
+    #[test]
+    fn test_len() {
+        assert_eq!(ranges(&[(3, 5), (10, 14), (16, 20), (12, 18)]).len(), 14);
+        assert_eq!(ranges(&[]).len(), 0);
+        assert_eq!(ranges(&[(0, u64::MAX)]).len(), u64::MAX as u128 + 1);
+        assert!(ranges(&[]).is_empty());
+        assert!(!ranges(&[(1, 1)]).is_empty());
+    }
+
+    #[test]
+    fn test_gaps() {
+        let r = ranges(&[(3, 5), (10, 14), (16, 20)]);
+        assert_eq!(
+            r.gaps().collect::<Vec<Range>>(),
+            vec![Range::new(6, 9), Range::new(15, 15)]
+        );
+        assert_eq!(ranges(&[(3, 5)]).gaps().count(), 0);
+        assert_eq!(ranges(&[]).gaps().count(), 0);
+
+        // uncombined input is combined first
+        assert_eq!(
+            ranges(&[(10, 14), (3, 5)]).gaps().collect::<Vec<Range>>(),
+            vec![Range::new(6, 9)]
+        );
+        assert_eq!(ranges(&[(0, 5), (0, 3)]).gaps().count(), 0);
+        assert_eq!(ranges(&[(0, 5), (6, 8)]).gaps().count(), 0);
+    }
+
+    #[test]
+    fn test_union() {
+        let a = ranges(&[(1, 5), (20, 25)]);
+        let b = ranges(&[(6, 8), (10, 21)]);
+        assert_eq!(a.union(&b), ranges(&[(1, 8), (10, 25)]));
+        assert_eq!(a.union(&ranges(&[])), a);
+    }
+
+    #[test]
+    fn test_intersection() {
+        let a = ranges(&[(1, 5), (8, 12), (20, 25)]);
+        let b = ranges(&[(4, 9), (11, 22), (30, 40)]);
+        assert_eq!(
+            a.intersection(&b),
+            ranges(&[(4, 5), (8, 9), (11, 12), (20, 22)])
+        );
+        assert_eq!(a.intersection(&ranges(&[])), ranges(&[]));
+    }
+
+    #[test]
+    fn test_complement() {
+        let a = ranges(&[(3, 5), (10, 14)]);
+        assert_eq!(
+            a.complement(&Range::new(0, 20)),
+            ranges(&[(0, 2), (6, 9), (15, 20)])
+        );
+        assert_eq!(a.complement(&Range::new(4, 12)), ranges(&[(6, 9)]));
+        assert_eq!(a.complement(&Range::new(3, 14)), ranges(&[(6, 9)]));
+        assert_eq!(ranges(&[]).complement(&Range::new(1, 2)), ranges(&[(1, 2)]));
+        assert_eq!(
+            ranges(&[(0, 5), (u64::MAX - 1, u64::MAX)]).complement(&Range::new(0, u64::MAX)),
+            ranges(&[(6, u64::MAX - 2)])
+        );
+    }
+
+    #[test]
+    fn test_difference() {
+        // fresh in a but spoiled in b
+        let a = ranges(&[(1, 10), (20, 30)]);
+        let b = ranges(&[(5, 22), (28, 28)]);
+        assert_eq!(a.difference(&b), ranges(&[(1, 4), (23, 27), (29, 30)]));
+        assert_eq!(b.difference(&a), ranges(&[(11, 19)]));
+        assert_eq!(a.difference(&a), ranges(&[]));
+    }
+
+    #[test]
+    fn test_symmetric_difference() {
+        let a = ranges(&[(1, 10), (20, 30)]);
+        let b = ranges(&[(5, 22)]);
+        assert_eq!(
+            a.symmetric_difference(&b),
+            ranges(&[(1, 4), (11, 19), (23, 30)])
+        );
+        assert_eq!(
+            a.symmetric_difference(&b).len(),
+            a.difference(&b).len() + b.difference(&a).len()
+        );
+    }
+
+    #[test]
+    fn test_interval_tree_insert() {
+        let mut tree = IntervalTree::new();
+        tree.insert(Range::new(10, 14));
+        tree.insert(Range::new(3, 5));
+        assert_eq!(tree.ranges(), ranges(&[(3, 5), (10, 14)]));
+
+        tree.insert(Range::new(6, 9));
+        assert_eq!(tree.ranges(), ranges(&[(3, 14)]));
+
+        tree.insert(Range::new(20, 20));
+        tree.insert(Range::new(1, 30));
+        assert_eq!(tree.ranges(), ranges(&[(1, 30)]));
+        assert!(tree.contains(1));
+        assert!(!tree.contains(31));
+    }
+
+    #[test]
+    fn test_interval_tree_remove() {
+        let mut tree = IntervalTree::new();
+        let a = tree.insert(Range::new(3, 5));
+        let b = tree.insert(Range::new(4, 12));
+        let c = tree.insert(Range::new(10, 14));
+        assert_eq!(tree.ranges(), ranges(&[(3, 14)]));
+
+        assert_eq!(tree.remove(b), Some(Range::new(4, 12)));
+        assert_eq!(tree.ranges(), ranges(&[(3, 5), (10, 14)]));
+        assert_eq!(tree.remove(b), None);
+
+        assert_eq!(tree.remove(a), Some(Range::new(3, 5)));
+        assert_eq!(tree.remove(c), Some(Range::new(10, 14)));
+        assert_eq!(tree.ranges(), ranges(&[]));
+
+        // the ends of u64
+        let all = tree.insert(Range::new(0, u64::MAX));
+        tree.insert(Range::new(5, 10));
+        tree.insert(Range::new(u64::MAX - 1, u64::MAX));
+        assert_eq!(tree.ranges(), ranges(&[(0, u64::MAX)]));
+        tree.remove(all);
+        assert_eq!(tree.ranges(), ranges(&[(5, 10), (u64::MAX - 1, u64::MAX)]));
+    }
+
+    #[test]
+    fn test_interval_tree_stab() {
+        let mut tree = IntervalTree::new();
+        let a = tree.insert(Range::new(3, 5));
+        let b = tree.insert(Range::new(10, 14));
+        let c = tree.insert(Range::new(16, 20));
+        let d = tree.insert(Range::new(12, 18));
+
+        assert_eq!(tree.stab(4), vec![(a, Range::new(3, 5))]);
+        assert_eq!(
+            tree.stab(13),
+            vec![(b, Range::new(10, 14)), (d, Range::new(12, 18))]
+        );
+        assert_eq!(
+            tree.stab(17),
+            vec![(d, Range::new(12, 18)), (c, Range::new(16, 20))]
+        );
+        assert_eq!(tree.stab(15), vec![(d, Range::new(12, 18))]);
+        assert_eq!(tree.stab(8), vec![]);
+
+        tree.remove(d);
+        assert_eq!(tree.stab(15), vec![]);
+        assert!(!tree.contains(15));
+    }
+
+    #[test]
+    fn test_interval_tree_matches_combine() {
+        let mut rng = Rng::new(0x853c49e6748fea9b);
+        let mut next = || rng.next_u64();
+
+        let mut tree = IntervalTree::new();
+        let mut live: Vec<(usize, Range)> = vec![];
+        for _ in 0..2000 {
+            if live.is_empty() || next() % 3 != 0 {
+                let start = next() % 500;
+                let range = Range::new(start, start + next() % 20);
+                live.push((tree.insert(range.clone()), range));
+            } else {
+                let (handle, range) = live.swap_remove((next() % live.len() as u64) as usize);
+                assert_eq!(tree.remove(handle), Some(range));
+            }
+
+            let mut expected = Ranges::new(live.iter().map(|(_, r)| r.clone()).collect());
+            expected.combine();
+            assert_eq!(tree.ranges(), expected);
+
+            let id = next() % 520;
+            let mut covering: Vec<usize> = live
+                .iter()
+                .filter(|(_, r)| r.start <= id && id <= r.end)
+                .map(|(h, _)| *h)
+                .collect();
+            let mut stabbed: Vec<usize> = tree.stab(id).iter().map(|(h, _)| *h).collect();
+            assert_eq!(tree.contains(id), !covering.is_empty());
+            covering.sort();
+            stabbed.sort();
+            assert_eq!(stabbed, covering);
+        }
+    }
+
+    #[test]
+    fn test_interval_tree_sorted_inserts() {
+        // inserting in order would make a plain search tree a list, the treap stays shallow
+        let mut tree = IntervalTree::new();
+        let handles: Vec<usize> = (0..20_000)
+            .map(|i| tree.insert(Range::new(i * 10, i * 10 + 15)))
+            .collect();
+        assert_eq!(tree.ranges(), ranges(&[(0, 200_005)]));
+        assert_eq!(tree.stab(100_012).len(), 2);
+        for h in handles.iter().step_by(2) {
+            tree.remove(*h);
+        }
+        assert_eq!(
+            tree.stab(100_012),
+            vec![(10_001, Range::new(100_010, 100_025))]
+        );
+        assert!(!tree.contains(100_008));
+    }
+
+    #[test]
+    fn test_interval_inclusive() {
+        let i = Interval::inclusive(3u8, 5);
+        assert_eq!((i.start(), i.end(), i.len()), (Some(3), Some(5), Some(3)));
+        assert!(i.contains(3) && i.contains(5) && !i.contains(6));
+
+        let i = Interval::inclusive(5u8, 3);
+        assert!(i.is_empty());
+        assert_eq!(i.len(), Some(0));
+        assert!(!i.contains(4));
+
+        assert_eq!(Interval::inclusive(7i32, 7).len(), Some(1));
+    }
+
+    #[test]
+    fn test_interval_half_open() {
+        assert_eq!(Interval::half_open(3u8, 6), Interval::inclusive(3, 5));
+        assert!(Interval::half_open(3u8, 3).is_empty());
+        assert!(Interval::half_open(0u8, 0).is_empty());
+        assert!(Interval::half_open(i8::MIN, i8::MIN).is_empty());
+        assert_eq!(Interval::half_open(-2i64, 2).len(), Some(4));
+        assert!(!Interval::half_open(-2i64, 2).contains(2));
+    }
+
+    #[test]
+    fn test_interval_len_full_domain() {
+        assert_eq!(
+            Interval::inclusive(0, u64::MAX).len(),
+            Some(u64::MAX as u128 + 1)
+        );
+        assert_eq!(
+            Interval::inclusive(i64::MIN, i64::MAX).len(),
+            Some(u64::MAX as u128 + 1)
+        );
+        assert_eq!(Interval::inclusive(i8::MIN, -1).len(), Some(128));
+        assert_eq!(Interval::inclusive(-1i128, 1).len(), Some(3));
+        assert_eq!(Interval::inclusive(0, u128::MAX - 1).len(), Some(u128::MAX));
+        assert_eq!(Interval::inclusive(0, u128::MAX).len(), None);
+        assert_eq!(Interval::inclusive(i128::MIN, i128::MAX).len(), None);
+    }
+
+    #[test]
+    fn test_range_interval() {
+        assert_eq!(Range::new(3, 5).interval(), Interval::inclusive(3, 5));
+        assert_eq!(Range::new(0, u64::MAX).interval().len(), Some(1 << 64));
+    }
+
+    #[test]
+    fn test_combine_adjacent_and_empty() {
+        let mut actual = Ranges::new(vec![Range::new(6, 8), Range::new(3, 5)]);
+        actual.combine();
+        assert_eq!(actual, Ranges::new(vec![Range::new(3, 8)]));
+
+        let mut actual = Ranges::new(vec![]);
+        actual.combine();
+        assert_eq!(actual, Ranges::new(vec![]));
+
+        let mut actual = Ranges::new(vec![
+            Range::new(20, 30),
+            Range::new(1, 2),
+            Range::new(22, 25),
+            Range::new(4, 4),
+            Range::new(u64::MAX - 1, u64::MAX),
+            Range::new(31, 31),
+            Range::new(3, 3),
+        ]);
+        actual.combine();
+        assert_eq!(
+            actual,
+            Ranges::new(vec![
+                Range::new(1, 4),
+                Range::new(20, 31),
+                Range::new(u64::MAX - 1, u64::MAX)
+            ])
+        );
+    }
 }
@@ -5,12 +5,15 @@
 //
 
 use std::{
-    fs::read,
-    io::{BufRead, Result},
+    collections::HashMap,
+    fmt,
+    fs::read_to_string,
+    io::{self, Result},
+    ops::Range,
     time::Instant,
 };
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum Operation {
     Add,
     Multiply,
@@ -28,195 +31,450 @@ impl Operation {
             _ => None,
         }
     }
-    pub fn operate(&self, a: &u64, b: &u64) -> u64 {
+    pub fn operate<T: Operand>(&self, a: &T, b: &T) -> std::result::Result<T, ArithmeticError> {
+        match self {
+            Self::Add => a.checked_add(*b).ok_or(ArithmeticError::Overflow),
+            Self::Subtract => a.checked_sub(*b).ok_or(ArithmeticError::Overflow),
+            Self::Divide if *b == T::ZERO => Err(ArithmeticError::DivideByZero),
+            Self::Divide => a.checked_div(*b).ok_or(ArithmeticError::Overflow),
+            Self::Multiply => a.checked_mul(*b).ok_or(ArithmeticError::Overflow),
+        }
+    }
+
+    // what an empty column scores, subtraction and division have nothing to start from
+    pub fn identity<T: Operand>(&self) -> Option<T> {
         match self {
-            Self::Add => a + b,
-            Self::Subtract => a - b,
-            Self::Divide => a / b,
-            Self::Multiply => a * b,
+            Self::Add => Some(T::ZERO),
+            Self::Multiply => Some(T::ONE),
+            Self::Subtract | Self::Divide => None,
         }
     }
 }
 
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ArithmeticError {
+    Overflow,
+    DivideByZero,
+    NoOperands,
+}
+
+impl fmt::Display for ArithmeticError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Overflow => write!(f, "the result does not fit"),
+            Self::DivideByZero => write!(f, "division by zero"),
+            Self::NoOperands => write!(f, "nothing to subtract or divide"),
+        }
+    }
+}
+
+// the integer types a column can be scored in, u128 leaves room for tall multiplications
+pub trait Operand: Copy + PartialEq + From<u64> {
+    const ZERO: Self;
+    const ONE: Self;
+    fn checked_add(self, rhs: Self) -> Option<Self>;
+    fn checked_sub(self, rhs: Self) -> Option<Self>;
+    fn checked_mul(self, rhs: Self) -> Option<Self>;
+    fn checked_div(self, rhs: Self) -> Option<Self>;
+}
+
+macro_rules! operand {
+    ($($t:ty),*) => {$(
+        impl Operand for $t {
+            const ZERO: Self = 0;
+            const ONE: Self = 1;
+            fn checked_add(self, rhs: Self) -> Option<Self> {
+                <$t>::checked_add(self, rhs)
+            }
+            fn checked_sub(self, rhs: Self) -> Option<Self> {
+                <$t>::checked_sub(self, rhs)
+            }
+            fn checked_mul(self, rhs: Self) -> Option<Self> {
+                <$t>::checked_mul(self, rhs)
+            }
+            fn checked_div(self, rhs: Self) -> Option<Self> {
+                <$t>::checked_div(self, rhs)
+            }
+        }
+    )*};
+}
+
+operand!(u64, u128);
+
+// which end of a column the fold starts from, top down is the order the numbers were read in
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum Reduction {
+    #[default]
+    TopDown,
+    BottomUp,
+}
+
 #[derive(Debug, PartialEq)]
 pub struct Column {
     nums: Vec<u64>,
     operation: Operation,
+    reduction: Reduction,
 }
 
 impl Column {
     pub fn new(nums: Vec<u64>, operation: Operation) -> Self {
-        Self { nums, operation }
+        Self {
+            nums,
+            operation,
+            reduction: Reduction::default(),
+        }
+    }
+
+    pub fn with_reduction(self, reduction: Reduction) -> Self {
+        Self { reduction, ..self }
+    }
+
+    pub fn score(&self) -> std::result::Result<u64, ArithmeticError> {
+        self.score_as()
     }
 
-    pub fn score(&self) -> u64 {
-        let mut s = 0;
-        if self.operation == Operation::Multiply {
-            s = 1;
+    // scores in a wider type so a column that overflows u64 can still be worked out
+    // folds from the first operand so 10 - 3 - 2 is 5 rather than 0 - 10 - 3 - 2
+    pub fn score_as<T: Operand>(&self) -> std::result::Result<T, ArithmeticError> {
+        match self.reduction {
+            Reduction::TopDown => self.fold(self.nums.iter()),
+            Reduction::BottomUp => self.fold(self.nums.iter().rev()),
         }
-        self.nums.iter().for_each(|n| {
-            s = self.operation.operate(&s, n);
-        });
-        s
     }
-}
-pub fn day_six(path: &str) -> Result<()> {
-    let now = Instant::now();
 
-    let content = read(path)?;
-    let lines: Vec<String> = content.lines().map(|l| l.unwrap()).collect();
-
-    let char_lines: Vec<Vec<char>> = lines
-        .iter()
-        .rev()
-        .map(|s| {
-            let cs: Vec<&str> = s.split_whitespace().collect();
-            let cs: Vec<char> = cs
-                .iter()
-                .map(|s| {
-                    let chars: Vec<char> = s.chars().collect();
-                    chars.first().unwrap().to_owned()
-                })
-                .collect();
-            cs
+    fn fold<'a, T: Operand>(
+        &self,
+        mut nums: impl Iterator<Item = &'a u64>,
+    ) -> std::result::Result<T, ArithmeticError> {
+        let Some(first) = nums.next() else {
+            return self.operation.identity().ok_or(ArithmeticError::NoOperands);
+        };
+        nums.try_fold(T::from(*first), |s, n| {
+            self.operation.operate(&s, &T::from(*n))
         })
-        .collect();
-
-    let operations: Vec<Operation> = char_lines
-        .first()
-        .unwrap()
-        .to_owned()
-        .iter()
-        .map(|a| Operation::from_char(a).unwrap())
-        .collect();
-
-    let columns: Vec<Vec<char>> = lines
-        .iter()
-        .rev()
-        .skip(1)
-        .map(|c| c.chars().rev().collect())
-        .rev()
-        .collect();
-
-    let rows = columns.len();
-    let cols = columns[0].len();
-
-    let transposed: Vec<Vec<char>> = (0..cols)
-        .map(|col| (0..rows).map(|row| columns[row][col]).collect())
-        .collect();
-
-    let transposed_filtered: Vec<Vec<char>> = transposed
-        .into_iter()
-        // .filter(|r| !r.iter().all(|i| i == &' ')) // get rid of this but then need a "_" on
-        // each line
-        .collect();
-
-    println!("{:?}", transposed_filtered.len());
-
-    let s: Vec<String> = transposed_filtered
-        .iter()
-        .map(|chunk| chunk.iter().collect())
-        .collect();
-
-    let ch = lines.len() - 1;
-
-    println!("{:?}", ch);
-
-    let splits: Vec<Vec<String>> = s
-        .join("\n") // combine lines into one string with line breaks
-        .split(" ".repeat(ch).as_str()) // split at 4 spaces
-        .map(|chunk| {
-            chunk
-                .lines() // split each chunk back into lines
-                .map(|line| line.to_string())
-                .filter(|line| !line.is_empty())
-                .collect::<Vec<String>>()
-        })
-        .collect();
-    println!("{:?}", splits);
-    //
-    let actual_nums: Vec<Vec<u64>> = splits
-        .iter()
-        .map(|s| {
-            s.iter()
-                .map(|n| n.trim_start().trim_end())
-                .map(|n| n.parse().unwrap())
-                .collect()
-        })
-        .collect();
-
-    let score: u64 = actual_nums
-        .iter()
-        .rev()
-        .zip(operations)
-        .map(|(t, op)| {
-            let s = Column::new(t.to_owned(), op).score();
-            println!("{:?}= {:?}", t, s);
-            s
-        })
-        .sum();
+    }
+}
 
-    println!(
-        "the score is {} and it took {}us",
-        score,
-        now.elapsed().as_micros()
-    );
+#[derive(Debug, PartialEq)]
+pub enum WorksheetError {
+    Empty,
+    InvalidDigit {
+        line: usize,
+        column: usize,
+        found: char,
+    },
+    InvalidOperator {
+        line: usize,
+        column: usize,
+        found: char,
+    },
+    MissingOperator {
+        column: usize,
+    },
+    ExtraOperator {
+        column: usize,
+    },
+    NumberTooLarge {
+        problem: usize,
+    },
+    Arithmetic {
+        problem: usize,
+        error: ArithmeticError,
+    },
+    TotalOverflow,
+    NoSuchProblem {
+        problem: usize,
+    },
+}
 
-    Ok(())
+impl fmt::Display for WorksheetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Empty => write!(
+                f,
+                "the worksheet needs rows of numbers above a row of operators"
+            ),
+            Self::InvalidDigit {
+                line,
+                column,
+                found,
+            } => write!(
+                f,
+                "line {} column {}: expected a digit or ' ' but found {:?}",
+                line, column, found
+            ),
+            Self::InvalidOperator {
+                line,
+                column,
+                found,
+            } => write!(
+                f,
+                "line {} column {}: expected an operator or ' ' but found {:?}",
+                line, column, found
+            ),
+            Self::MissingOperator { column } => {
+                write!(
+                    f,
+                    "the problem starting at column {} has no operator",
+                    column
+                )
+            }
+            Self::ExtraOperator { column } => write!(
+                f,
+                "column {}: a second operator in the same problem, is a blank column missing?",
+                column
+            ),
+            Self::NumberTooLarge { problem } => {
+                write!(f, "problem {}: a number does not fit in a u64", problem)
+            }
+            Self::Arithmetic { problem, error } => write!(f, "problem {}: {}", problem, error),
+            Self::TotalOverflow => write!(f, "the sum of the problems does not fit"),
+            Self::NoSuchProblem { problem } => {
+                write!(f, "there is no problem {} on the worksheet", problem)
+            }
+        }
+    }
 }
 
-pub fn day_six_part_1(path: &str) -> Result<()> {
-    let now = Instant::now();
+// a problem covers a run of columns between blank columns, with its operator somewhere under it
+#[derive(Debug, PartialEq)]
+struct Problem {
+    span: Range<usize>,
+    operation: Operation,
+}
+
+// part one reads each line of a problem as a number, part two reads each column
+// of digits top to bottom as a number going from right to left
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum ReadingOrder {
+    RowWise,
+    RightToLeft,
+}
+
+// the number rows are kept as they are, lines can be shorter than the worksheet
+// and anything past the end of a line is treated as blank
+#[derive(Debug, PartialEq)]
+pub struct Worksheet {
+    lines: Vec<Vec<char>>,
+    problems: Vec<Problem>,
+    reduction: Reduction,
+    overrides: HashMap<usize, Reduction>, // problem index -> reduction
+}
+
+impl Worksheet {
+    pub fn read(input: &str) -> std::result::Result<Self, WorksheetError> {
+        let mut lines: Vec<Vec<char>> = input
+            .trim_end_matches(['\n', '\r'])
+            .lines()
+            .map(|l| l.chars().collect())
+            .collect();
+        let operators = lines.pop().ok_or(WorksheetError::Empty)?;
+        if lines.is_empty() {
+            return Err(WorksheetError::Empty);
+        }
+
+        for (j, line) in lines.iter().enumerate() {
+            if let Some(i) = line.iter().position(|c| *c != ' ' && !c.is_ascii_digit()) {
+                return Err(WorksheetError::InvalidDigit {
+                    line: j + 1,
+                    column: i + 1,
+                    found: line[i],
+                });
+            }
+        }
+        let operations = operators
+            .iter()
+            .enumerate()
+            .map(|(i, c)| match c {
+                ' ' => Ok(None),
+                c => Operation::from_char(c)
+                    .map(Some)
+                    .ok_or(WorksheetError::InvalidOperator {
+                        line: lines.len() + 1,
+                        column: i + 1,
+                        found: *c,
+                    }),
+            })
+            .collect::<std::result::Result<Vec<Option<Operation>>, WorksheetError>>()?;
+
+        let width = lines
+            .iter()
+            .map(|l| l.len())
+            .chain([operations.len()])
+            .max()
+            .unwrap_or(0);
+        let blank = |i: usize| {
+            operations.get(i).is_none_or(|o| o.is_none())
+                && lines.iter().all(|l| l.get(i).is_none_or(|c| *c == ' '))
+        };
+
+        let mut problems = Vec::new();
+        let mut i = 0;
+        while i < width {
+            if blank(i) {
+                i += 1;
+                continue;
+            }
+            let start = i;
+            while i < width && !blank(i) {
+                i += 1;
+            }
+
+            let mut found = (start..i).filter(|i| operations.get(*i).is_some_and(|o| o.is_some()));
+            let Some(at) = found.next() else {
+                return Err(WorksheetError::MissingOperator { column: start + 1 });
+            };
+            if let Some(extra) = found.next() {
+                return Err(WorksheetError::ExtraOperator { column: extra + 1 });
+            }
+            let operation = Operation::from_char(&operators[at]).unwrap();
+            problems.push(Problem {
+                span: start..i,
+                operation,
+            });
+        }
 
-    let content = read(path)?;
-    let lines: Vec<String> = content.lines().map(|l| l.unwrap()).collect();
-
-    let char_lines: Vec<Vec<char>> = lines
-        .iter()
-        .rev()
-        .map(|s| {
-            let cs: Vec<&str> = s.split_whitespace().collect();
-            let cs: Vec<char> = cs
-                .iter()
-                .map(|s| {
-                    let chars: Vec<char> = s.chars().collect();
-                    chars.first().unwrap().to_owned()
-                })
-                .collect();
-            cs
+        Ok(Self {
+            lines,
+            problems,
+            reduction: Reduction::default(),
+            overrides: HashMap::new(),
         })
-        .collect();
-    let operations: Vec<Operation> = char_lines
-        .first()
-        .unwrap()
-        .to_owned()
-        .iter()
-        .map(|a| Operation::from_char(a).unwrap())
-        .collect();
-
-    let columns: Vec<Vec<u64>> = lines
-        .iter()
-        .rev()
-        .skip(1)
-        .map(|c| c.split_whitespace().map(|a| a.parse().unwrap()).collect())
-        .collect();
-
-    let rows = columns.len();
-    let cols = columns[0].len();
-
-    let transposed: Vec<Vec<u64>> = (0..cols)
-        .map(|col| (0..rows).map(|row| columns[row][col]).collect())
-        .collect();
-
-    let score: u64 = transposed
-        .iter()
-        .zip(operations)
-        .map(|(t, op)| Column::new(t.to_owned(), op).score())
-        .sum();
+    }
+
+    // every column the worksheet yields is folded in this direction unless its
+    // problem has been given its own
+    pub fn with_reduction(self, reduction: Reduction) -> Self {
+        Self { reduction, ..self }
+    }
+
+    // problems are numbered from 1 left to right, the same as in the errors
+    pub fn with_problem_reduction(
+        mut self,
+        problem: usize,
+        reduction: Reduction,
+    ) -> std::result::Result<Self, WorksheetError> {
+        if problem == 0 || problem > self.problems.len() {
+            return Err(WorksheetError::NoSuchProblem { problem });
+        }
+        self.overrides.insert(problem - 1, reduction);
+        Ok(self)
+    }
+
+    fn reduction(&self, problem: usize) -> Reduction {
+        self.overrides
+            .get(&problem)
+            .copied()
+            .unwrap_or(self.reduction)
+    }
+
+    fn cell(&self, line: usize, column: usize) -> char {
+        self.lines[line].get(column).copied().unwrap_or(' ')
+    }
+
+    pub fn columns(&self, order: ReadingOrder) -> std::result::Result<Vec<Column>, WorksheetError> {
+        match order {
+            ReadingOrder::RowWise => self.row_wise(),
+            ReadingOrder::RightToLeft => self.right_to_left(),
+        }
+    }
+
+    pub fn score<T: Operand>(&self, order: ReadingOrder) -> std::result::Result<T, WorksheetError> {
+        self.columns(order)?
+            .iter()
+            .enumerate()
+            .try_fold(T::ZERO, |total, (p, c)| {
+                let s = c.score_as().map_err(|error| WorksheetError::Arithmetic {
+                    problem: p + 1,
+                    error,
+                })?;
+                total.checked_add(s).ok_or(WorksheetError::TotalOverflow)
+            })
+    }
+
+    // numbers read across each line, a line can hold more than one number in a problem
+    fn row_wise(&self) -> std::result::Result<Vec<Column>, WorksheetError> {
+        self.problems
+            .iter()
+            .enumerate()
+            .map(|(p, problem)| {
+                let nums = (0..self.lines.len())
+                    .flat_map(|j| {
+                        let row: String = problem.span.clone().map(|i| self.cell(j, i)).collect();
+                        row.split_whitespace()
+                            .map(|n| {
+                                n.parse()
+                                    .map_err(|_| WorksheetError::NumberTooLarge { problem: p + 1 })
+                            })
+                            .collect::<Vec<_>>()
+                    })
+                    .collect::<std::result::Result<Vec<u64>, WorksheetError>>()?;
+                Ok(Column::new(nums, problem.operation.clone()).with_reduction(self.reduction(p)))
+            })
+            .collect()
+    }
+
+    // numbers read down each column, taking the columns from right to left
+    fn right_to_left(&self) -> std::result::Result<Vec<Column>, WorksheetError> {
+        self.problems
+            .iter()
+            .enumerate()
+            .map(|(p, problem)| {
+                let nums = problem
+                    .span
+                    .clone()
+                    .rev()
+                    .filter_map(|i| {
+                        let digits: String = (0..self.lines.len())
+                            .map(|j| self.cell(j, i))
+                            .filter(|c| *c != ' ')
+                            .collect();
+                        (!digits.is_empty()).then(|| {
+                            digits
+                                .parse()
+                                .map_err(|_| WorksheetError::NumberTooLarge { problem: p + 1 })
+                        })
+                    })
+                    .collect::<std::result::Result<Vec<u64>, WorksheetError>>()?;
+                Ok(Column::new(nums, problem.operation.clone()).with_reduction(self.reduction(p)))
+            })
+            .collect()
+    }
+}
+
+fn scores<T: Operand>(worksheet: &Worksheet) -> std::result::Result<(T, T), WorksheetError> {
+    Ok((
+        worksheet.score(ReadingOrder::RowWise)?,
+        worksheet.score(ReadingOrder::RightToLeft)?,
+    ))
+}
+
+// wide scores in u128, for worksheets whose problems overflow a u64. every problem
+// folds in the reduction direction apart from those listed in bottom_up
+pub fn day_six(path: &str, wide: bool, reduction: Reduction, bottom_up: &[usize]) -> Result<()> {
+    let content = read_to_string(path)?;
+
+    let now = Instant::now();
+    let invalid = |e: WorksheetError| io::Error::new(io::ErrorKind::InvalidData, e.to_string());
+    let worksheet = bottom_up.iter().try_fold(
+        Worksheet::read(&content)
+            .map_err(invalid)?
+            .with_reduction(reduction),
+        |w, p| w.with_problem_reduction(*p, Reduction::BottomUp),
+    );
+    let worksheet = worksheet.map_err(invalid)?;
+    let (row_wise, right_to_left) = if wide {
+        let (r, c) = scores::<u128>(&worksheet).map_err(invalid)?;
+        (r.to_string(), c.to_string())
+    } else {
+        let (r, c) = scores::<u64>(&worksheet).map_err(invalid)?;
+        (r.to_string(), c.to_string())
+    };
 
     println!(
-        "the score is {} and it took {}us",
-        score,
+        "the row wise score is {}, the right to left score is {}, and it took {}us",
+        row_wise,
+        right_to_left,
         now.elapsed().as_micros()
     );
 
@@ -238,10 +496,46 @@ mod test {
 
     #[test]
     fn test_operation_operate() {
-        assert_eq!(Operation::from_char(&'+').unwrap().operate(&5, &10), 15);
-        assert_eq!(Operation::from_char(&'-').unwrap().operate(&10, &5), 5);
-        assert_eq!(Operation::from_char(&'*').unwrap().operate(&5, &10), 50);
-        assert_eq!(Operation::from_char(&'/').unwrap().operate(&10, &5), 2);
+        assert_eq!(
+            Operation::from_char(&'+').unwrap().operate(&5u64, &10),
+            Ok(15)
+        );
+        assert_eq!(
+            Operation::from_char(&'-').unwrap().operate(&10u64, &5),
+            Ok(5)
+        );
+        assert_eq!(
+            Operation::from_char(&'*').unwrap().operate(&5u64, &10),
+            Ok(50)
+        );
+        assert_eq!(
+            Operation::from_char(&'/').unwrap().operate(&10u64, &5),
+            Ok(2)
+        );
+    }
+
+    #[test]
+    fn test_operation_operate_checked() {
+        assert_eq!(
+            Operation::Add.operate(&u64::MAX, &1),
+            Err(ArithmeticError::Overflow)
+        );
+        assert_eq!(
+            Operation::Subtract.operate(&5u64, &10),
+            Err(ArithmeticError::Overflow)
+        );
+        assert_eq!(
+            Operation::Multiply.operate(&u64::MAX, &2),
+            Err(ArithmeticError::Overflow)
+        );
+        assert_eq!(
+            Operation::Divide.operate(&5u64, &0),
+            Err(ArithmeticError::DivideByZero)
+        );
+        assert_eq!(
+            Operation::Multiply.operate(&(u64::MAX as u128), &2),
+            Ok(u64::MAX as u128 * 2)
+        );
     }
 
     #[test]
@@ -251,6 +545,7 @@ mod test {
             Column {
                 nums: vec![1, 2, 3, 4, 5],
                 operation: Operation::Add,
+                reduction: Reduction::TopDown,
             }
         )
     }
@@ -258,9 +553,248 @@ mod test {
     #[test]
     fn test_column_score() {
         let c = Column::new(vec![1, 2, 3, 4, 5], Operation::Add);
-        assert_eq!(c.score(), 15);
+        assert_eq!(c.score(), Ok(15));
 
         let c = Column::new(vec![1, 2, 3, 4, 5], Operation::Multiply);
-        assert_eq!(c.score(), 120);
+        assert_eq!(c.score(), Ok(120));
+
+        let c = Column::new(vec![10, 3, 2], Operation::Subtract);
+        assert_eq!(c.score(), Ok(5));
+
+        let c = Column::new(vec![100, 5, 2], Operation::Divide);
+        assert_eq!(c.score(), Ok(10));
+    }
+
+    #[test]
+    fn test_column_score_bottom_up() {
+        let c =
+            Column::new(vec![1, 2, 3, 4, 5], Operation::Add).with_reduction(Reduction::BottomUp);
+        assert_eq!(c.score(), Ok(15));
+
+        let c = Column::new(vec![1, 2, 3, 4, 5], Operation::Multiply)
+            .with_reduction(Reduction::BottomUp);
+        assert_eq!(c.score(), Ok(120));
+
+        let c =
+            Column::new(vec![2, 3, 10], Operation::Subtract).with_reduction(Reduction::BottomUp);
+        assert_eq!(c.score(), Ok(5));
+        let c =
+            Column::new(vec![10, 3, 2], Operation::Subtract).with_reduction(Reduction::BottomUp);
+        assert_eq!(c.score(), Err(ArithmeticError::Overflow));
+
+        let c = Column::new(vec![2, 5, 100], Operation::Divide).with_reduction(Reduction::BottomUp);
+        assert_eq!(c.score(), Ok(10));
+        let c = Column::new(vec![100, 5, 2], Operation::Divide).with_reduction(Reduction::BottomUp);
+        assert_eq!(c.score(), Ok(0));
+    }
+
+    #[test]
+    fn test_column_score_edges() {
+        assert_eq!(Column::new(vec![], Operation::Add).score(), Ok(0));
+        assert_eq!(Column::new(vec![], Operation::Multiply).score(), Ok(1));
+        assert_eq!(
+            Column::new(vec![], Operation::Subtract).score(),
+            Err(ArithmeticError::NoOperands)
+        );
+        assert_eq!(
+            Column::new(vec![], Operation::Divide).score(),
+            Err(ArithmeticError::NoOperands)
+        );
+
+        // a single operand is the score whatever the operation
+        assert_eq!(Column::new(vec![7], Operation::Subtract).score(), Ok(7));
+        assert_eq!(Column::new(vec![7], Operation::Divide).score(), Ok(7));
+        assert_eq!(Column::new(vec![0], Operation::Divide).score(), Ok(0));
+
+        assert_eq!(
+            Column::new(vec![7, 0], Operation::Divide).score(),
+            Err(ArithmeticError::DivideByZero)
+        );
+    }
+
+    #[test]
+    fn test_column_score_as() {
+        let c = Column::new(vec![u64::MAX, u64::MAX], Operation::Multiply);
+        assert_eq!(c.score(), Err(ArithmeticError::Overflow));
+        assert_eq!(
+            c.score_as::<u128>(),
+            Ok(u64::MAX as u128 * u64::MAX as u128)
+        );
+    }
+
+    const WORKSHEET: &str = "123 328  51 64 \n 45 64  387 23 \n  6 98  215 314\n*   +   *   +  \n";
+
+    #[test]
+    fn test_worksheet_read() {
+        let w = Worksheet::read(WORKSHEET).unwrap();
+        assert_eq!(
+            w.problems,
+            vec![
+                Problem {
+                    span: 0..3,
+                    operation: Operation::Multiply
+                },
+                Problem {
+                    span: 4..7,
+                    operation: Operation::Add
+                },
+                Problem {
+                    span: 8..11,
+                    operation: Operation::Multiply
+                },
+                Problem {
+                    span: 12..15,
+                    operation: Operation::Add
+                },
+            ]
+        );
+
+        // trailing spaces trimmed off the lines
+        let ragged = "123 328  51 64\n 45 64  387 23\n  6 98  215 314\n*   +   *   +";
+        assert_eq!(Worksheet::read(ragged).unwrap().problems, w.problems);
+        assert_eq!(
+            Worksheet::read(&ragged.replace('\n', "\r\n")).unwrap(),
+            Worksheet::read(ragged).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_worksheet_read_errors() {
+        assert_eq!(Worksheet::read(""), Err(WorksheetError::Empty));
+        assert_eq!(Worksheet::read("* +"), Err(WorksheetError::Empty));
+        assert_eq!(
+            Worksheet::read("12 3x\n*  +"),
+            Err(WorksheetError::InvalidDigit {
+                line: 1,
+                column: 5,
+                found: 'x'
+            })
+        );
+        assert_eq!(
+            Worksheet::read("12 34\n*  %"),
+            Err(WorksheetError::InvalidOperator {
+                line: 2,
+                column: 4,
+                found: '%'
+            })
+        );
+        assert_eq!(
+            Worksheet::read("12 34\n*   "),
+            Err(WorksheetError::MissingOperator { column: 4 })
+        );
+        assert_eq!(
+            Worksheet::read("12 34\n*+ +"),
+            Err(WorksheetError::ExtraOperator { column: 2 })
+        );
+    }
+
+    #[test]
+    fn test_worksheet_columns_row_wise() {
+        let w = Worksheet::read(WORKSHEET).unwrap();
+        assert_eq!(
+            w.columns(ReadingOrder::RowWise),
+            Ok(vec![
+                Column::new(vec![123, 45, 6], Operation::Multiply),
+                Column::new(vec![328, 64, 98], Operation::Add),
+                Column::new(vec![51, 387, 215], Operation::Multiply),
+                Column::new(vec![64, 23, 314], Operation::Add),
+            ])
+        );
+        assert_eq!(w.score(ReadingOrder::RowWise), Ok(4277556u64));
+
+        let w = Worksheet::read("123456789012345678901\n+").unwrap();
+        assert_eq!(
+            w.columns(ReadingOrder::RowWise),
+            Err(WorksheetError::NumberTooLarge { problem: 1 })
+        );
+    }
+
+    #[test]
+    fn test_worksheet_columns_right_to_left() {
+        let w = Worksheet::read(WORKSHEET).unwrap();
+        assert_eq!(
+            w.columns(ReadingOrder::RightToLeft),
+            Ok(vec![
+                Column::new(vec![356, 24, 1], Operation::Multiply),
+                Column::new(vec![8, 248, 369], Operation::Add),
+                Column::new(vec![175, 581, 32], Operation::Multiply),
+                Column::new(vec![4, 431, 623], Operation::Add),
+            ])
+        );
+        assert_eq!(w.score(ReadingOrder::RightToLeft), Ok(3263827u64));
+
+        // the short middle line leaves gaps rather than panicking
+        let w = Worksheet::read("12 4\n3\n*  +").unwrap();
+        assert_eq!(
+            w.columns(ReadingOrder::RightToLeft),
+            Ok(vec![
+                Column::new(vec![2, 13], Operation::Multiply),
+                Column::new(vec![4], Operation::Add),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_worksheet_score_errors() {
+        let w = Worksheet::read("1 9999999999999\n2 9999999999999\n+ *").unwrap();
+        assert_eq!(
+            w.score::<u64>(ReadingOrder::RowWise),
+            Err(WorksheetError::Arithmetic {
+                problem: 2,
+                error: ArithmeticError::Overflow
+            })
+        );
+        assert_eq!(
+            w.score::<u128>(ReadingOrder::RowWise),
+            Ok(9999999999999u128 * 9999999999999 + 3)
+        );
+
+        let w = Worksheet::read("8\n0\n/").unwrap();
+        assert_eq!(
+            w.score::<u64>(ReadingOrder::RowWise),
+            Err(WorksheetError::Arithmetic {
+                problem: 1,
+                error: ArithmeticError::DivideByZero
+            })
+        );
+
+        let w = Worksheet::read("18446744073709551615 1\n+                    +").unwrap();
+        assert_eq!(
+            w.score::<u64>(ReadingOrder::RowWise),
+            Err(WorksheetError::TotalOverflow)
+        );
+    }
+
+    #[test]
+    fn test_worksheet_problem_reduction() {
+        // 2 / 5 / 100 rounds down to 0
+        let w = Worksheet::read("10 2\n 3 5\n 2 100\n-  /").unwrap();
+        assert_eq!(w.score::<u64>(ReadingOrder::RowWise), Ok(5));
+
+        // 10 - 3 - 2 top down and 100 / 5 / 2 bottom up
+        let w = w.with_problem_reduction(2, Reduction::BottomUp).unwrap();
+        assert_eq!(
+            w.columns(ReadingOrder::RowWise),
+            Ok(vec![
+                Column::new(vec![10, 3, 2], Operation::Subtract),
+                Column::new(vec![2, 5, 100], Operation::Divide).with_reduction(Reduction::BottomUp),
+            ])
+        );
+        assert_eq!(w.score::<u64>(ReadingOrder::RowWise), Ok(15));
+
+        // problems without their own follow the worksheet, 2 - 3 underflows
+        let w = w.with_reduction(Reduction::BottomUp);
+        assert_eq!(
+            w.score::<u64>(ReadingOrder::RowWise),
+            Err(WorksheetError::Arithmetic {
+                problem: 1,
+                error: ArithmeticError::Overflow
+            })
+        );
+
+        assert_eq!(
+            w.with_problem_reduction(3, Reduction::TopDown).err(),
+            Some(WorksheetError::NoSuchProblem { problem: 3 })
+        );
     }
 }
@@ -7,3 +7,6 @@ pub mod day6;
 pub mod day7;
 pub mod day8;
 pub mod day9;
+
+#[cfg(test)]
+mod test_util;
@@ -0,0 +1,19 @@
+// small seeded xorshift so the cross check tests dont need a rand dependency
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    // xorshift gets stuck on zero so the seed shouldn't be
+    pub fn new(seed: u64) -> Self {
+        assert_ne!(seed, 0);
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        self.state
+    }
+}
@@ -1,28 +1,101 @@
 use std::{env, io};
 
 use aoc25::days::{
-    day1::day_one, day2::day_two, day3::day_three, day4::day_four, day5::day_five, day6::day_six,
-    day7::day_seven, day8::day_eight, day9::day_nine,
+    day1::day_one,
+    day2::day_two,
+    day3::day_three,
+    day4::{Neighbourhood, RemovalRule, day_four},
+    day5::day_five,
+    day6::{Reduction, day_six},
+    day7::day_seven,
+    day8::day_eight,
+    day9::day_nine,
 };
 
 fn main() -> io::Result<()> {
     let mut args = env::args();
-    let (Some(_), Some(day), Some(path), None) =
-        (args.next(), args.next(), args.next(), args.next())
-    else {
+    let (Some(_), Some(day), Some(path)) = (args.next(), args.next(), args.next()) else {
         return Err(io::Error::new(
             io::ErrorKind::InvalidInput,
             "Expected day arg",
         ));
     };
+    let flags: Vec<String> = args.collect();
+
+    // the flags each day takes, those that take a value and those that are switches
+    let (values, switches): (&[&str], &[&str]) = match day.as_str() {
+        "day_three" => (&["--pick"], &["--render"]),
+        "day_four" => (
+            &["--threshold", "--neighbourhood"],
+            &["--history", "--wrap"],
+        ),
+        "day_five" => (&[], &["--swap-inverted"]),
+        "day_six" => (&["--bottom-up-problems"], &["--wide", "--bottom-up"]),
+        _ => (&[], &[]),
+    };
+    check_flags(&flags, values, switches)?;
 
     match day.as_str() {
         "day_one" => day_one(path.as_str())?,
         "day_two" => day_two(path.as_str())?,
-        "day_three" => day_three(path.as_str())?,
-        "day_four" => day_four(path.as_str())?,
-        "day_five" => day_five(path.as_str())?,
-        "day_six" => day_six(path.as_str())?,
+        "day_three" => {
+            let pick = match flag_value(&flags, "--pick") {
+                Some(k) => k.parse().map_err(|_| {
+                    io::Error::new(io::ErrorKind::InvalidInput, "Expected --pick k")
+                })?,
+                None => 12,
+            };
+            day_three(path.as_str(), pick, has_flag(&flags, "--render"))?
+        }
+        "day_four" => {
+            let threshold = match flag_value(&flags, "--threshold") {
+                Some(n) => n.parse().map_err(|_| {
+                    io::Error::new(io::ErrorKind::InvalidInput, "Expected --threshold n")
+                })?,
+                None => 4,
+            };
+            let neighbourhood = match flag_value(&flags, "--neighbourhood") {
+                Some(n) => Neighbourhood::read(n).ok_or(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "Expected --neighbourhood moore|von-neumann|dx,dy;dx,dy",
+                ))?,
+                None => Neighbourhood::Moore,
+            };
+            let rule = RemovalRule::new(threshold, neighbourhood);
+            day_four(
+                path.as_str(),
+                &rule,
+                has_flag(&flags, "--history"),
+                has_flag(&flags, "--wrap"),
+            )?
+        }
+        "day_five" => day_five(path.as_str(), has_flag(&flags, "--swap-inverted"))?,
+        "day_six" => {
+            let reduction = if has_flag(&flags, "--bottom-up") {
+                Reduction::BottomUp
+            } else {
+                Reduction::TopDown
+            };
+            let bottom_up = match flag_value(&flags, "--bottom-up-problems") {
+                Some(ps) => ps
+                    .split(',')
+                    .map(|p| p.trim().parse())
+                    .collect::<Result<Vec<usize>, _>>()
+                    .map_err(|_| {
+                        io::Error::new(
+                            io::ErrorKind::InvalidInput,
+                            "Expected --bottom-up-problems n,n",
+                        )
+                    })?,
+                None => vec![],
+            };
+            day_six(
+                path.as_str(),
+                has_flag(&flags, "--wide"),
+                reduction,
+                &bottom_up,
+            )?
+        }
         "day_seven" => day_seven(path.as_str())?,
         "day_eight" => day_eight(path.as_str())?,
         "day_nine" => day_nine(path.as_str())?,
@@ -35,3 +108,37 @@ fn main() -> io::Result<()> {
     }
     Ok(())
 }
+
+// rejects anything that isn't one of the day's flags, and value flags with nothing after them
+fn check_flags(flags: &[String], values: &[&str], switches: &[&str]) -> io::Result<()> {
+    let mut flags = flags.iter();
+    while let Some(f) = flags.next() {
+        if values.contains(&f.as_str()) {
+            if flags.next().is_none() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("Expected a value after {}", f),
+                ));
+            }
+        } else if !switches.contains(&f.as_str()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Unknown flag {}", f),
+            ));
+        }
+    }
+    Ok(())
+}
+
+// finds the value following a flag i.e. `--pick 2`
+fn flag_value<'a>(flags: &'a [String], flag: &str) -> Option<&'a str> {
+    flags
+        .iter()
+        .position(|f| f == flag)
+        .and_then(|i| flags.get(i + 1))
+        .map(|v| v.as_str())
+}
+
+fn has_flag(flags: &[String], flag: &str) -> bool {
+    flags.iter().any(|f| f == flag)
+}